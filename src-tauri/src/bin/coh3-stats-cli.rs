@@ -0,0 +1,73 @@
+use coh3_stats_desktop_app::log_watcher::LogWatcher;
+use coh3_stats_desktop_app::parse_log_file::{parse_all_matches, parse_log_file_reverse};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::thread;
+
+const USAGE: &str = "Usage: coh3-stats-cli <command> <file>
+
+Commands:
+  parse <file>    print the current game of the log file as JSON
+  history <file>  print every match of the log file as JSON
+  watch <file>    print newline-delimited JSON events whenever the log file changes";
+
+/// A line printed by the watch command
+#[derive(Serialize)]
+struct WatchLine<'a, T: Serialize> {
+    event: &'a str,
+    payload: &'a T,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, path) = match args.as_slice() {
+        [command, path] => (command.as_str(), path.clone()),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let result = match command {
+        "parse" => parse_log_file_reverse(path).map(|data| print_json(&data)),
+        "history" => parse_all_matches(path).map(|matches| print_json(&matches)),
+        "watch" => watch(path),
+        _ => {
+            eprintln!("Unknown command: {}\n\n{}", command, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    // Ignore a closed stdout, e.g. when piped into head
+    writeln!(
+        io::stdout(),
+        "{}",
+        serde_json::to_string_pretty(value).unwrap()
+    )
+    .ok();
+}
+
+/// prints every game event until the process is stopped, errors are printed as events as well
+fn watch(path: String) -> ! {
+    let _watcher = LogWatcher::spawn(path, |event| {
+        let line = WatchLine {
+            event: event.name(),
+            payload: &event,
+        };
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", serde_json::to_string(&line).unwrap()).ok();
+        stdout.flush().ok();
+    });
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,145 @@
+//! The Tauri commands of the app, thin wrappers around the library that hold the app state
+
+use crate::game_process::SysinfoProbe;
+use crate::log_discovery::{self, LogFileCandidate};
+use crate::log_import::{self, ImportError, ImportReport};
+use crate::log_validation::{self, LogFileReport};
+use crate::match_history::{HistoryFilter, HistoryPage, MatchHistory, MatchHistoryError};
+use crate::match_start_message::{self, MatchStartMessage, MatchStartMessageError};
+use crate::overlay_server::OverlayServerState;
+use crate::parse_log_file::{self, LogFileData, LogLine, LogTailer, MatchRecord, ParseError};
+use crate::relic_api::{EnrichedTeams, RelicApiClient, RelicApiError};
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::Manager;
+
+/// The log tailer used by the `parse_log_file_tail` command, kept between the calls of the frontend
+#[derive(Default)]
+pub struct LogTailerState(pub Mutex<Option<LogTailer>>);
+
+/// The match history of the app, `None` when the database could not be opened
+#[derive(Default)]
+pub struct MatchHistoryState(pub Mutex<Option<MatchHistory>>);
+
+/// searches the known locations of the log file, the most recently modified one comes first
+#[tauri::command]
+pub fn discover_log_files() -> Vec<LogFileCandidate> {
+    log_discovery::discover_log_files()
+}
+
+/// reports whether the path can be used as the log file and what is wrong with it otherwise
+#[tauri::command(async)]
+pub fn validate_log_file(path: String) -> LogFileReport {
+    log_validation::validate_log_file(path, &mut SysinfoProbe::new())
+}
+
+#[tauri::command]
+pub fn parse_log_file_reverse(path: String) -> Result<LogFileData, ParseError> {
+    parse_log_file::parse_log_file_reverse(path)
+}
+
+#[tauri::command]
+pub fn parse_all_matches(path: String) -> Result<Vec<MatchRecord>, ParseError> {
+    parse_log_file::parse_all_matches(path)
+}
+
+/// returns the last warning and error lines the game logged in the running session
+#[tauri::command]
+pub fn get_recent_game_errors(
+    path: String,
+    limit: usize,
+    subsystem: Option<String>,
+) -> Result<Vec<LogLine>, ParseError> {
+    parse_log_file::recent_game_errors(path, limit, subsystem)
+}
+
+/// parses only the lines appended since the last call for the same path
+#[tauri::command]
+pub fn parse_log_file_tail(
+    path: String,
+    tailer: tauri::State<LogTailerState>,
+) -> Result<LogFileData, ParseError> {
+    let mut tailer = tailer.0.lock().unwrap();
+    match tailer.as_mut() {
+        Some(current) if current.path() == Path::new(&path) => current.poll(),
+        _ => tailer.insert(LogTailer::new(path)).poll(),
+    }
+}
+
+#[tauri::command]
+pub fn parse_match_start_message(
+    message: String,
+) -> Result<MatchStartMessage, MatchStartMessageError> {
+    match_start_message::parse_match_start_message(message)
+}
+
+/// returns one page of the stored matches matching the filter
+#[tauri::command]
+pub fn get_match_history(
+    filter: HistoryFilter,
+    history: tauri::State<MatchHistoryState>,
+) -> Result<HistoryPage, MatchHistoryError> {
+    match history.0.lock().unwrap().as_ref() {
+        Some(history) => history.query(&filter),
+        None => Err(MatchHistoryError::NotOpen),
+    }
+}
+
+/// stores the matches of every log file in the folder that are not in the match history yet,
+/// emits an `import-progress` event after each parsed file
+#[tauri::command(async)]
+pub fn import_logs(
+    dir: String,
+    app: tauri::AppHandle,
+    history: tauri::State<MatchHistoryState>,
+) -> Result<ImportReport, ImportError> {
+    if history.0.lock().unwrap().is_none() {
+        return Err(MatchHistoryError::NotOpen.into());
+    }
+    // The match history stays usable while the log files are parsed
+    let parsed = log_import::parse_logs(dir, |progress| {
+        app.emit_all("import-progress", progress).ok();
+    })?;
+    let mut history = history.0.lock().unwrap();
+    let history = history.as_mut().ok_or(MatchHistoryError::NotOpen)?;
+    parsed.store(history)
+}
+
+/// fills the steam id, rank and profile of every human player from the Relic API,
+/// runs off the main thread so the window does not freeze while waiting for the API
+#[tauri::command(async)]
+pub fn enrich_log_file_data(
+    mut data: LogFileData,
+    client: tauri::State<RelicApiClient>,
+) -> Result<LogFileData, RelicApiError> {
+    client.enrich_log_file_data(&mut data)?;
+    Ok(data)
+}
+
+/// requests the stats of every human player in the log file data from the Relic API,
+/// runs off the main thread so the window does not freeze while waiting for the API
+#[tauri::command(async)]
+pub fn get_enriched_players(
+    data: LogFileData,
+    client: tauri::State<RelicApiClient>,
+) -> Result<EnrichedTeams, RelicApiError> {
+    client.enrich(&data)
+}
+
+/// starts the overlay server on the given port or stops it
+#[tauri::command]
+pub fn set_overlay_server(
+    enabled: bool,
+    port: u16,
+    overlay: tauri::State<OverlayServerState>,
+) -> Result<(), String> {
+    overlay
+        .configure(enabled, port)
+        .map_err(|error| error.to_string())
+}
+
+/// replaces the overlay page served by the overlay server
+#[tauri::command]
+pub fn set_overlay_html(html: String, overlay: tauri::State<OverlayServerState>) {
+    overlay.content.set_html(html);
+}
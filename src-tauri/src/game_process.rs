@@ -0,0 +1,189 @@
+use crate::parse_log_file::{parse_log_line, GameState, LogLevel};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+
+/// The names of the game process, Proton runs the Windows executable as well
+pub const GAME_PROCESS_NAMES: [&str; 2] = ["RelicCoH3.exe", "RelicCoH3"];
+
+// The game writes "Application closed" just before its process exits,
+// a log file written this recently is not considered crashed yet
+const EXIT_GRACE_PERIOD: Duration = Duration::from_secs(15);
+
+// Without a process check the game is only assumed gone when it did not log anything this long
+const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// The errors are searched in this many bytes at the end of the log file
+const EXCERPT_SEARCH_LENGTH: u64 = 64 * 1024;
+
+const EXCERPT_MAX_LINES: usize = 20;
+
+/// Checks if the game process is running
+pub trait ProcessProbe {
+    /// `None` when the processes of the system can not be checked
+    fn is_game_running(&mut self) -> Option<bool>;
+}
+
+/// Checks the processes of the system for the game executable
+pub struct SysinfoProbe {
+    system: System,
+}
+
+impl SysinfoProbe {
+    pub fn new() -> Self {
+        SysinfoProbe {
+            system: System::new(),
+        }
+    }
+}
+
+impl Default for SysinfoProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessProbe for SysinfoProbe {
+    fn is_game_running(&mut self) -> Option<bool> {
+        if !sysinfo::IS_SUPPORTED_SYSTEM {
+            return None;
+        }
+        self.system.refresh_processes();
+        Some(
+            GAME_PROCESS_NAMES
+                .iter()
+                .any(|name| self.system.processes_by_exact_name(name).next().is_some()),
+        )
+    }
+}
+
+/// What was logged before the game crashed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CrashReport {
+    /// unix timestamp in seconds of the last write to the log file
+    pub last_activity: Option<u64>,
+    /// the last error lines of the log file, oldest first
+    pub excerpt: Vec<String>,
+}
+
+/// corrects the state parsed from the log file when the log file stopped without the close line,
+/// `last_activity` is the last time the log file was written
+pub fn assess_game_state(
+    parsed: &GameState,
+    last_activity: Option<SystemTime>,
+    now: SystemTime,
+    probe: &mut dyn ProcessProbe,
+) -> GameState {
+    if *parsed == GameState::Closed {
+        return GameState::Closed;
+    }
+    let inactive_for = last_activity
+        .and_then(|last_activity| now.duration_since(last_activity).ok())
+        .unwrap_or_default();
+    match probe.is_game_running() {
+        Some(true) => parsed.clone(),
+        Some(false) if inactive_for > EXIT_GRACE_PERIOD => GameState::Crashed,
+        None if inactive_for > INACTIVITY_TIMEOUT => GameState::Unknown,
+        _ => parsed.clone(),
+    }
+}
+
+/// collects the error lines logged before the log file stopped
+pub fn crash_report(path: &Path, last_activity: Option<SystemTime>) -> CrashReport {
+    CrashReport {
+        last_activity: last_activity
+            .and_then(|last_activity| last_activity.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs()),
+        excerpt: error_excerpt(path).unwrap_or_default(),
+    }
+}
+
+/// the last error lines at the end of the log file, oldest first
+pub fn error_excerpt(path: &Path) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+    file.seek(SeekFrom::Start(
+        length.saturating_sub(EXCERPT_SEARCH_LENGTH),
+    ))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+    let errors: Vec<&str> = tail
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| parse_log_line(line).is_some_and(|line| line.level == LogLevel::Error))
+        .collect();
+    Ok(errors[errors.len().saturating_sub(EXCERPT_MAX_LINES)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assess_game_state, error_excerpt, ProcessProbe};
+    use crate::parse_log_file::GameState;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    struct MockProbe(Option<bool>);
+
+    impl ProcessProbe for MockProbe {
+        fn is_game_running(&mut self) -> Option<bool> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_assess_game_state() {
+        let now = SystemTime::now();
+        let assess = |parsed: GameState, inactive_secs: u64, running: Option<bool>| {
+            let last_activity = now - Duration::from_secs(inactive_secs);
+            assess_game_state(&parsed, Some(last_activity), now, &mut MockProbe(running))
+        };
+        assert_eq!(
+            assess(GameState::InGame, 3600, Some(true)),
+            GameState::InGame
+        );
+        assert_eq!(
+            assess(GameState::InGame, 60, Some(false)),
+            GameState::Crashed
+        );
+        assert_eq!(assess(GameState::Menu, 60, Some(false)), GameState::Crashed);
+        // The game may still be writing the close line
+        assert_eq!(assess(GameState::Menu, 5, Some(false)), GameState::Menu);
+        assert_eq!(
+            assess(GameState::Closed, 60, Some(false)),
+            GameState::Closed
+        );
+        assert_eq!(assess(GameState::Loading, 60, None), GameState::Loading);
+        assert_eq!(assess(GameState::Loading, 3600, None), GameState::Unknown);
+    }
+
+    #[test]
+    fn test_error_excerpt() {
+        let path =
+            std::env::temp_dir().join(format!("coh3-stats-excerpt-{}.log", std::process::id()));
+        let mut log = "RelicCoH3 started at 2023-05-10 18:20\n".to_string();
+        for index in 0..25 {
+            log.push_str(&format!(
+                "(E) [18:25:{:02}.000] [000012345]: SIM -- Error {}\r\n",
+                index, index
+            ));
+            log.push_str("(I) [18:25:30.000] [000012345]: GAME -- Info\r\n");
+        }
+        fs::write(&path, log).unwrap();
+        let excerpt = error_excerpt(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(excerpt.len(), 20);
+        assert_eq!(excerpt[0], "(E) [18:25:05.000] [000012345]: SIM -- Error 5");
+        assert_eq!(
+            excerpt[19],
+            "(E) [18:25:24.000] [000012345]: SIM -- Error 24"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::parse_log_file::{Faction, PlayerData, TeamData};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// The ids of the ranked leaderboards from 1v1 to 4v4,
+// each with the factions german, americans, afrika_korps and british.
+// Taken from the names the Relic API lists them with, like `2v2_american` for 2130300,
+// see `/community/leaderboard/getAvailableLeaderboards?title=coh3`. The ids of the americans
+// come before the german ones from 2v2 on, unlike in 1v1.
+const LEADERBOARD_IDS: [[u64; 4]; 4] = [
+    [2130255, 2130257, 2130259, 2130261],
+    [2130302, 2130300, 2130304, 2130306],
+    [2130331, 2130329, 2130333, 2130335],
+    [2130356, 2130353, 2130358, 2130360],
+];
+
+/// The mode of a game, only games of two human teams with the same size are ranked
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    #[serde(rename = "1v1")]
+    OneVsOne,
+    #[serde(rename = "2v2")]
+    TwoVsTwo,
+    #[serde(rename = "3v3")]
+    ThreeVsThree,
+    #[serde(rename = "4v4")]
+    FourVsFour,
+    /// at least one player is an AI
+    AI,
+    /// the teams have different sizes, are empty or have more than 4 players
+    Custom,
+}
+
+impl GameMode {
+    /// derives the mode from both teams of a game
+    pub fn from_teams(left: &TeamData, right: &TeamData) -> Self {
+        let players = || left.players.iter().chain(right.players.iter());
+        if players().any(|player| player.ai) {
+            return GameMode::AI;
+        }
+        if left.players.len() != right.players.len() {
+            return GameMode::Custom;
+        }
+        match left.players.len() {
+            1 => GameMode::OneVsOne,
+            2 => GameMode::TwoVsTwo,
+            3 => GameMode::ThreeVsThree,
+            4 => GameMode::FourVsFour,
+            _ => GameMode::Custom,
+        }
+    }
+
+    /// the number of players per team, `None` for unranked modes
+    pub fn team_size(&self) -> Option<usize> {
+        match self {
+            GameMode::OneVsOne => Some(1),
+            GameMode::TwoVsTwo => Some(2),
+            GameMode::ThreeVsThree => Some(3),
+            GameMode::FourVsFour => Some(4),
+            GameMode::AI | GameMode::Custom => None,
+        }
+    }
+
+    /// the leaderboard of the faction in this mode, `None` for unranked modes and unknown factions
+    pub fn leaderboard_id(&self, faction: &Faction) -> Option<u64> {
+        // British on africa maps are ranked on the british leaderboards
+        let faction_index = match faction {
+            Faction::Wehrmacht | Faction::WestGerman => 0,
+            Faction::Americans => 1,
+            Faction::AfrikaKorps => 2,
+            Faction::British | Faction::BritishAfrica => 3,
+            Faction::Unknown(_) => return None,
+        };
+        Some(LEADERBOARD_IDS[self.team_size()? - 1][faction_index])
+    }
+
+    /// the leaderboard the player is ranked on in this mode, `None` for AI players
+    pub fn player_leaderboard_id(&self, player: &PlayerData) -> Option<u64> {
+        if player.ai {
+            return None;
+        }
+        self.leaderboard_id(&player.faction)
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameMode::OneVsOne => f.write_str("1v1"),
+            GameMode::TwoVsTwo => f.write_str("2v2"),
+            GameMode::ThreeVsThree => f.write_str("3v3"),
+            GameMode::FourVsFour => f.write_str("4v4"),
+            GameMode::AI => f.write_str("AI"),
+            GameMode::Custom => f.write_str("Custom"),
+        }
+    }
+}
+
+/// resolves the leaderboard of every player of both teams, in the order of the players
+pub fn resolve_leaderboard_ids(
+    left: &TeamData,
+    right: &TeamData,
+) -> (Vec<Option<u64>>, Vec<Option<u64>>) {
+    let mode = GameMode::from_teams(left, right);
+    let resolve = |team: &TeamData| {
+        team.players
+            .iter()
+            .map(|player| mode.player_leaderboard_id(player))
+            .collect()
+    };
+    (resolve(left), resolve(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_leaderboard_ids, GameMode};
+    use crate::parse_log_file::{get_team_data, Faction, PlayerData, PlayerProfile, TeamData};
+
+    fn player(faction: Faction, ai: bool) -> PlayerData {
+        PlayerData {
+            ai,
+            faction,
+            relic_id: if ai { "-1" } else { "1" }.to_string(),
+            name: "Player".to_string(),
+            position: 0,
+            steam_id: "".to_string(),
+            rank: -1,
+            profile: PlayerProfile::default(),
+        }
+    }
+
+    fn team(factions: &[Faction]) -> TeamData {
+        get_team_data(
+            factions
+                .iter()
+                .map(|faction| player(faction.clone(), false))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_game_mode_from_teams() {
+        let allies = team(&[Faction::Americans, Faction::British]);
+        let axis = team(&[Faction::Wehrmacht, Faction::AfrikaKorps]);
+        assert_eq!(GameMode::from_teams(&allies, &axis), GameMode::TwoVsTwo);
+        assert_eq!(
+            GameMode::from_teams(&team(&[Faction::British]), &team(&[Faction::Wehrmacht])),
+            GameMode::OneVsOne
+        );
+        assert_eq!(
+            GameMode::from_teams(&allies, &team(&[Faction::Wehrmacht])),
+            GameMode::Custom
+        );
+        assert_eq!(
+            GameMode::from_teams(&team(&[]), &team(&[])),
+            GameMode::Custom
+        );
+        let five = vec![Faction::Americans; 5];
+        assert_eq!(
+            GameMode::from_teams(&team(&five), &team(&five)),
+            GameMode::Custom
+        );
+        let with_ai = get_team_data(vec![
+            player(Faction::Wehrmacht, false),
+            player(Faction::AfrikaKorps, true),
+        ]);
+        assert_eq!(GameMode::from_teams(&allies, &with_ai), GameMode::AI);
+    }
+
+    #[test]
+    fn test_leaderboard_id() {
+        assert_eq!(
+            GameMode::OneVsOne.leaderboard_id(&Faction::Wehrmacht),
+            Some(2130255)
+        );
+        assert_eq!(
+            GameMode::TwoVsTwo.leaderboard_id(&Faction::Americans),
+            Some(2130300)
+        );
+        assert_eq!(
+            GameMode::ThreeVsThree.leaderboard_id(&Faction::AfrikaKorps),
+            Some(2130333)
+        );
+        assert_eq!(
+            GameMode::FourVsFour.leaderboard_id(&Faction::Wehrmacht),
+            Some(2130356)
+        );
+        assert_eq!(
+            GameMode::OneVsOne.leaderboard_id(&Faction::WestGerman),
+            GameMode::OneVsOne.leaderboard_id(&Faction::Wehrmacht)
+        );
+        assert_eq!(
+            GameMode::FourVsFour.leaderboard_id(&Faction::BritishAfrica),
+            GameMode::FourVsFour.leaderboard_id(&Faction::British)
+        );
+        assert_eq!(
+            GameMode::OneVsOne.leaderboard_id(&Faction::Unknown("soviet".to_string())),
+            None
+        );
+        assert_eq!(GameMode::Custom.leaderboard_id(&Faction::British), None);
+        assert_eq!(GameMode::AI.leaderboard_id(&Faction::British), None);
+    }
+
+    #[test]
+    fn test_resolve_leaderboard_ids() {
+        let (left, right) = resolve_leaderboard_ids(
+            &team(&[Faction::Americans, Faction::British]),
+            &team(&[Faction::Wehrmacht, Faction::AfrikaKorps]),
+        );
+        assert_eq!(left, [Some(2130300), Some(2130306)]);
+        assert_eq!(right, [Some(2130302), Some(2130304)]);
+
+        let (left, right) = resolve_leaderboard_ids(
+            &team(&[Faction::Americans]),
+            &get_team_data(vec![player(Faction::Wehrmacht, true)]),
+        );
+        assert_eq!(left, [None]);
+        assert_eq!(right, [None]);
+        assert_eq!(
+            serde_json::to_string(&GameMode::ThreeVsThree).unwrap(),
+            "\"3v3\""
+        );
+    }
+}
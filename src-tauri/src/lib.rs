@@ -1 +1,25 @@
+#[cfg(feature = "tauri")]
+pub mod commands;
+#[cfg(feature = "watcher")]
+pub mod game_process;
+pub mod leaderboards;
+#[cfg(feature = "archive")]
+pub mod log_archive;
+#[cfg(feature = "discovery")]
+pub mod log_discovery;
+#[cfg(feature = "import")]
+pub mod log_import;
+#[cfg(feature = "watcher")]
+pub mod log_validation;
+#[cfg(feature = "watcher")]
+pub mod log_watcher;
+#[cfg(feature = "history")]
+pub mod match_history;
+pub mod match_start_message;
+#[cfg(feature = "overlay-server")]
+pub mod overlay_server;
 pub mod parse_log_file;
+#[cfg(feature = "relic-api")]
+pub mod relic_api;
+#[cfg(feature = "wasm")]
+pub mod wasm;
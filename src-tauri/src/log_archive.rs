@@ -0,0 +1,355 @@
+use crate::parse_log_file::{parse_line, parse_session_start, LogEvent};
+use chrono::{DateTime, Local, NaiveDateTime};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// The copy of the running session, it is compressed when the game starts a new one
+const STAGING_FILE_NAME: &str = "current.log";
+
+// The path of the log file the copy was taken from
+const STAGING_SOURCE_FILE_NAME: &str = "current.source";
+
+// The first bytes of the log tell the sessions apart,
+// they contain the launch time and the time codes of the first lines
+const IDENTITY_LENGTH: u64 = 1024;
+
+/// How many archives are kept, the oldest ones are removed first
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveConfig {
+    pub max_files: usize,
+    /// the size of all archives together in bytes
+    pub max_bytes: u64,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        ArchiveConfig {
+            max_files: 30,
+            max_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Keeps the sessions of a log file the game would overwrite on its next launch.
+///
+/// Every [`poll`](LogArchiver::poll) copies what was added to the log file into the archive dir,
+/// along with the path of the log file, so a copy of another log file is never archived.
+/// When the log file was replaced or truncated, the copy of the previous session is compressed
+/// into a `warnings_<date>.log.gz` archive named by the launch time of that session.
+pub struct LogArchiver {
+    log_path: PathBuf,
+    archive_dir: PathBuf,
+    config: ArchiveConfig,
+}
+
+impl LogArchiver {
+    pub fn new(
+        log_path: impl Into<PathBuf>,
+        archive_dir: impl Into<PathBuf>,
+        config: ArchiveConfig,
+    ) -> Self {
+        LogArchiver {
+            log_path: log_path.into(),
+            archive_dir: archive_dir.into(),
+            config,
+        }
+    }
+
+    pub fn archive_dir(&self) -> &Path {
+        &self.archive_dir
+    }
+
+    /// copies the new lines of the log file and archives the previous session when a new one
+    /// started, returns the path of the archive that was written
+    pub fn poll(&mut self) -> io::Result<Option<PathBuf>> {
+        let mut log = match File::open(&self.log_path) {
+            Ok(log) => log,
+            // The game may not have created the log file yet
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let log_length = log.metadata()?.len();
+        let staging_path = self.archive_dir.join(STAGING_FILE_NAME);
+        let source_path = self.archive_dir.join(STAGING_SOURCE_FILE_NAME);
+        // The copy of another log file is not a session of this one and is dropped,
+        // that log file still holds the session
+        if staging_path.exists() && !self.is_staging_source(&source_path) {
+            fs::remove_file(&staging_path)?;
+        }
+        let mut staged_length = fs::metadata(&staging_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+
+        let mut archive = None;
+        if staged_length > 0
+            && (log_length < staged_length || !same_session(&mut log, &staging_path)?)
+        {
+            archive = Some(self.archive_staged(&staging_path)?);
+            self.apply_retention()?;
+            staged_length = 0;
+        }
+
+        if log_length > staged_length {
+            fs::create_dir_all(&self.archive_dir)?;
+            if staged_length == 0 {
+                fs::write(&source_path, self.log_path.to_string_lossy().as_bytes())?;
+            }
+            let mut staging = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&staging_path)?;
+            log.seek(SeekFrom::Start(staged_length))?;
+            io::copy(&mut log.take(log_length - staged_length), &mut staging)?;
+        }
+        Ok(archive)
+    }
+
+    fn is_staging_source(&self, source_path: &Path) -> bool {
+        fs::read_to_string(source_path)
+            .is_ok_and(|source| source == self.log_path.to_string_lossy())
+    }
+
+    /// compresses the copy of the previous session and removes it
+    fn archive_staged(&self, staging_path: &Path) -> io::Result<PathBuf> {
+        let started_at = session_started_at(staging_path)?;
+        let name = started_at.format("warnings_%Y-%m-%d_%H-%M").to_string();
+        let mut path = self.archive_dir.join(format!("{}.log.gz", name));
+        // Two launches within the same minute
+        let mut count = 1;
+        while path.exists() {
+            count += 1;
+            path = self.archive_dir.join(format!("{}_{}.log.gz", name, count));
+        }
+
+        let mut encoder = GzEncoder::new(File::create(&path)?, Compression::default());
+        io::copy(&mut File::open(staging_path)?, &mut encoder)?;
+        encoder.finish()?;
+        fs::remove_file(staging_path)?;
+        Ok(path)
+    }
+
+    /// removes the oldest archives until both limits of the config are met
+    fn apply_retention(&self) -> io::Result<()> {
+        let mut archives = archived_log_files(&self.archive_dir)?
+            .into_iter()
+            .map(|path| {
+                let size = fs::metadata(&path)?.len();
+                Ok((path, size))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut total_bytes: u64 = archives.iter().map(|(_, size)| size).sum();
+        archives.reverse();
+        while archives.len() > self.config.max_files || total_bytes > self.config.max_bytes {
+            let Some((path, size)) = archives.pop() else {
+                break;
+            };
+            fs::remove_file(path)?;
+            total_bytes -= size;
+        }
+        Ok(())
+    }
+}
+
+/// the archives in the archive dir, the oldest session comes first
+pub fn archived_log_files(archive_dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(archive_dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut archives = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_archive = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("warnings_") && name.ends_with(".log.gz"));
+        if is_archive {
+            archives.push(path);
+        }
+    }
+    // The names start with the date of the session
+    archives.sort();
+    Ok(archives)
+}
+
+// Compares the start of the log file with the start of the copy
+fn same_session(log: &mut File, staging_path: &Path) -> io::Result<bool> {
+    let mut log_head = Vec::new();
+    log.seek(SeekFrom::Start(0))?;
+    log.take(IDENTITY_LENGTH).read_to_end(&mut log_head)?;
+    let mut staged_head = Vec::new();
+    File::open(staging_path)?
+        .take(IDENTITY_LENGTH)
+        .read_to_end(&mut staged_head)?;
+    let length = log_head.len().min(staged_head.len());
+    Ok(log_head[..length] == staged_head[..length])
+}
+
+// The launch time from the header of the session, or when the copy was last written
+fn session_started_at(staging_path: &Path) -> io::Result<NaiveDateTime> {
+    let mut header = String::new();
+    BufReader::new(File::open(staging_path)?).read_line(&mut header)?;
+    if let Some(LogEvent::SessionStarted { started_at }) = parse_line(header.trim_end()) {
+        if let Some(started_at) = parse_session_start(&started_at) {
+            return Ok(started_at);
+        }
+    }
+    let modified = fs::metadata(staging_path)?.modified()?;
+    Ok(DateTime::<Local>::from(modified).naive_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{archived_log_files, ArchiveConfig, LogArchiver};
+    use flate2::read::GzDecoder;
+    use std::fs::{self, OpenOptions};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+
+    const FIRST_SESSION: &str = "RelicCoH3 started at 2023-05-10 18:20
+(I) [18:20:01.234] [000012345]: GAME -- [Company of Heroes 3] set to language [en]
+";
+
+    const SECOND_SESSION: &str = "RelicCoH3 started at 2023-05-11 09:05
+(I) [09:05:02.345] [000012345]: GAME -- [Company of Heroes 3] set to language [en]
+";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "coh3-stats-archive-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_archive(path: &Path) -> String {
+        let mut content = String::new();
+        GzDecoder::new(fs::File::open(path).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_log_archiver() {
+        let dir = temp_dir("sessions");
+        let log_path = dir.join("warnings.log");
+        let archive_dir = dir.join("log_archive");
+        let mut archiver = LogArchiver::new(&log_path, &archive_dir, ArchiveConfig::default());
+
+        // Nothing to do before the game created the log file
+        assert_eq!(archiver.poll().unwrap(), None);
+
+        fs::write(&log_path, FIRST_SESSION).unwrap();
+        assert_eq!(archiver.poll().unwrap(), None);
+        let appended = "(I) [18:20:05.000] [000012345]: Match Started\n";
+        OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .unwrap()
+            .write_all(appended.as_bytes())
+            .unwrap();
+        assert_eq!(archiver.poll().unwrap(), None);
+
+        // The game truncates the log file on launch
+        fs::write(&log_path, SECOND_SESSION).unwrap();
+        let archive = archiver.poll().unwrap().unwrap();
+        assert_eq!(
+            archive,
+            archive_dir.join("warnings_2023-05-10_18-20.log.gz")
+        );
+        assert_eq!(
+            read_archive(&archive),
+            format!("{}{}", FIRST_SESSION, appended)
+        );
+        assert_eq!(
+            fs::read_to_string(archive_dir.join("current.log")).unwrap(),
+            SECOND_SESSION
+        );
+
+        // A new session with a longer first line is noticed by its content
+        let third_session = SECOND_SESSION.replace("2023-05-11 09:05", "2023-05-11 09:05 ");
+        fs::write(&log_path, third_session).unwrap();
+        assert_eq!(
+            archiver.poll().unwrap(),
+            Some(archive_dir.join("warnings_2023-05-11_09-05.log.gz"))
+        );
+        assert_eq!(archived_log_files(&archive_dir).unwrap().len(), 2);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_log_archiver_other_log_file() {
+        let dir = temp_dir("other");
+        let first_path = dir.join("warnings.log");
+        let second_path = dir.join("other.log");
+        let archive_dir = dir.join("log_archive");
+        fs::write(&first_path, FIRST_SESSION).unwrap();
+        fs::write(&second_path, SECOND_SESSION).unwrap();
+        LogArchiver::new(&first_path, &archive_dir, ArchiveConfig::default())
+            .poll()
+            .unwrap();
+
+        // Another log file is selected, the copy of the first one is not archived as its session
+        let mut archiver = LogArchiver::new(&second_path, &archive_dir, ArchiveConfig::default());
+        assert_eq!(archiver.poll().unwrap(), None);
+        assert!(archived_log_files(&archive_dir).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(archive_dir.join("current.log")).unwrap(),
+            SECOND_SESSION
+        );
+
+        fs::write(&second_path, FIRST_SESSION).unwrap();
+        assert_eq!(
+            archiver.poll().unwrap(),
+            Some(archive_dir.join("warnings_2023-05-11_09-05.log.gz"))
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_log_archiver_retention() {
+        let dir = temp_dir("retention");
+        let log_path = dir.join("warnings.log");
+        let archive_dir = dir.join("log_archive");
+        fs::create_dir_all(&archive_dir).unwrap();
+        for day in 1..=3 {
+            fs::write(
+                archive_dir.join(format!("warnings_2023-05-0{}_12-00.log.gz", day)),
+                [0; 100],
+            )
+            .unwrap();
+        }
+        let config = ArchiveConfig {
+            max_files: 3,
+            max_bytes: 300,
+        };
+        let mut archiver = LogArchiver::new(&log_path, &archive_dir, config);
+        fs::write(&log_path, FIRST_SESSION).unwrap();
+        archiver.poll().unwrap();
+        fs::write(&log_path, SECOND_SESSION).unwrap();
+        archiver.poll().unwrap();
+
+        // Four archives are too many and three are too large
+        let names: Vec<String> = archived_log_files(&archive_dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "warnings_2023-05-03_12-00.log.gz",
+                "warnings_2023-05-10_18-20.log.gz"
+            ]
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The steam app id of Company of Heroes 3, Proton keeps a wine prefix per app id
+pub const COH3_STEAM_APP_ID: u32 = 1677280;
+
+// The log file inside a Documents folder
+const LOG_FILE_PATH: [&str; 3] = ["My Games", "Company of Heroes 3", "warnings.log"];
+
+// OneDrive names the Documents folder in the language of the system
+const LOCALIZED_DOCUMENTS: [&str; 6] = [
+    "Documents",
+    "Dokumente",
+    "Documentos",
+    "Documenti",
+    "Documenten",
+    "Dokumenty",
+];
+
+/// Why a path is a candidate for the log file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CandidateReason {
+    /// the Documents folder of the user as the system reports it,
+    /// this follows folders moved to OneDrive or another location
+    DocumentsFolder,
+    /// a Documents folder inside OneDrive
+    OneDrive,
+    /// the Documents folder in the home folder of the user
+    HomeDocuments,
+    /// the Documents folder of the Proton prefix of a Steam library
+    Proton,
+    /// the Documents folder of the default Wine prefix
+    Wine,
+}
+
+/// A log file found by [`discover_log_files`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogFileCandidate {
+    pub path: String,
+    pub reason: CandidateReason,
+    /// unix timestamp in seconds of the last modification, `None` if the system does not know it
+    pub modified: Option<u64>,
+}
+
+/// The folders the candidates are searched in, taken from the system by [`SearchRoots::from_system`]
+#[derive(Clone, Debug, Default)]
+pub struct SearchRoots {
+    pub documents: Option<PathBuf>,
+    pub home: Option<PathBuf>,
+    /// the OneDrive folders of the user, there is one for personal and one for business accounts
+    pub one_drive: Vec<PathBuf>,
+    /// the Steam install folders, more libraries are read from their `libraryfolders.vdf`
+    pub steam: Vec<PathBuf>,
+}
+
+impl SearchRoots {
+    pub fn from_system() -> Self {
+        let home = dirs::home_dir();
+        let one_drive = ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"]
+            .iter()
+            .filter_map(|variable| env::var_os(variable).map(PathBuf::from))
+            .chain(home.iter().map(|home| home.join("OneDrive")))
+            .collect();
+        let steam = home
+            .iter()
+            .flat_map(|home| {
+                [
+                    home.join(".steam/steam"),
+                    home.join(".local/share/Steam"),
+                    // Steam installed with Flatpak
+                    home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+                ]
+            })
+            .collect();
+        SearchRoots {
+            documents: dirs::document_dir(),
+            home,
+            one_drive,
+            steam,
+        }
+    }
+}
+
+/// Searches the known locations of the log file, the most recently modified log file comes first
+pub fn discover_log_files() -> Vec<LogFileCandidate> {
+    discover_log_files_in(&SearchRoots::from_system())
+}
+
+/// Searches the locations below the roots, see [`discover_log_files`]
+pub fn discover_log_files_in(roots: &SearchRoots) -> Vec<LogFileCandidate> {
+    let mut seen = HashSet::new();
+    let mut candidates: Vec<LogFileCandidate> = candidate_paths(roots)
+        .into_iter()
+        .filter_map(|(path, reason)| {
+            let metadata = fs::metadata(&path)
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            // The same file can be reached in several ways, e.g. through symlinked Steam folders
+            let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if !seen.insert(canonical) {
+                return None;
+            }
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs());
+            Some(LogFileCandidate {
+                path: path.display().to_string(),
+                reason,
+                modified,
+            })
+        })
+        .collect();
+    // The sort is stable, so candidates with the same or no modification time keep the search order
+    candidates.sort_by_key(|candidate| Reverse(candidate.modified));
+    candidates
+}
+
+// Every path the log file could be at, in the order they are searched
+fn candidate_paths(roots: &SearchRoots) -> Vec<(PathBuf, CandidateReason)> {
+    let mut paths = Vec::new();
+    if let Some(documents) = &roots.documents {
+        paths.push((log_file_in(documents), CandidateReason::DocumentsFolder));
+    }
+    for one_drive in &roots.one_drive {
+        for documents in LOCALIZED_DOCUMENTS {
+            paths.push((
+                log_file_in(&one_drive.join(documents)),
+                CandidateReason::OneDrive,
+            ));
+        }
+    }
+    if let Some(home) = &roots.home {
+        for documents in LOCALIZED_DOCUMENTS {
+            paths.push((
+                log_file_in(&home.join(documents)),
+                CandidateReason::HomeDocuments,
+            ));
+        }
+    }
+    for library in steam_libraries(&roots.steam) {
+        let prefix_documents = library
+            .join("steamapps/compatdata")
+            .join(COH3_STEAM_APP_ID.to_string())
+            .join("pfx/drive_c/users/steamuser/Documents");
+        paths.push((log_file_in(&prefix_documents), CandidateReason::Proton));
+    }
+    if let Some(home) = &roots.home {
+        let users = home.join(".wine/drive_c/users");
+        let user_names = fs::read_dir(&users)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name());
+        for user_name in user_names {
+            let documents = users.join(user_name).join("Documents");
+            paths.push((log_file_in(&documents), CandidateReason::Wine));
+        }
+    }
+    paths
+}
+
+fn log_file_in(documents: &Path) -> PathBuf {
+    LOG_FILE_PATH
+        .iter()
+        .fold(documents.to_path_buf(), |path, part| path.join(part))
+}
+
+// The Steam folders and every library listed in their `libraryfolders.vdf`
+fn steam_libraries(steam: &[PathBuf]) -> Vec<PathBuf> {
+    let mut libraries = Vec::new();
+    for steam in steam {
+        libraries.push(steam.clone());
+        let Ok(library_folders) = fs::read_to_string(steam.join("steamapps/libraryfolders.vdf"))
+        else {
+            continue;
+        };
+        libraries.extend(library_folders.lines().filter_map(library_path));
+    }
+    libraries.dedup();
+    libraries
+}
+
+// Library lines look like this: "path"		"/mnt/games/SteamLibrary"
+fn library_path(line: &str) -> Option<PathBuf> {
+    let value = line.trim().strip_prefix("\"path\"")?.trim();
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some(PathBuf::from(value.replace("\\\\", "\\")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{discover_log_files_in, library_path, CandidateReason, SearchRoots};
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    fn create_log(documents: &Path, modified: SystemTime) -> PathBuf {
+        let folder = documents.join("My Games").join("Company of Heroes 3");
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("warnings.log");
+        let file = File::create(&path).unwrap();
+        file.set_modified(modified).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_discover_log_files() {
+        let root = std::env::temp_dir().join(format!("coh3-stats-discover-{}", std::process::id()));
+        let home = root.join("home");
+        let steam = home.join(".local/share/Steam");
+        let library = root.join("games/SteamLibrary");
+        fs::create_dir_all(steam.join("steamapps")).unwrap();
+        fs::write(
+            steam.join("steamapps/libraryfolders.vdf"),
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                library.display()
+            ),
+        )
+        .unwrap();
+
+        let now = SystemTime::now();
+        let home_log = create_log(&home.join("Documents"), now - Duration::from_secs(3600));
+        let proton_log = create_log(
+            &library.join("steamapps/compatdata/1677280/pfx/drive_c/users/steamuser/Documents"),
+            now,
+        );
+        let roots = SearchRoots {
+            // The same folder as the home Documents is only listed once
+            documents: Some(home.join("Documents")),
+            home: Some(home.clone()),
+            one_drive: vec![home.join("OneDrive")],
+            steam: vec![steam],
+        };
+        let candidates = discover_log_files_in(&roots);
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].path, proton_log.display().to_string());
+        assert_eq!(candidates[0].reason, CandidateReason::Proton);
+        assert_eq!(candidates[1].path, home_log.display().to_string());
+        assert_eq!(candidates[1].reason, CandidateReason::DocumentsFolder);
+        assert!(candidates[0].modified > candidates[1].modified);
+    }
+
+    #[test]
+    fn test_library_path() {
+        assert_eq!(
+            library_path("\t\t\"path\"\t\t\"D:\\\\SteamLibrary\""),
+            Some(PathBuf::from("D:\\SteamLibrary"))
+        );
+        assert_eq!(library_path("\t\t\"label\"\t\t\"\""), None);
+    }
+}
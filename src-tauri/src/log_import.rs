@@ -0,0 +1,279 @@
+use crate::match_history::{HistoryMatch, MatchHistory, MatchHistoryError};
+use crate::parse_log_file::{parse_all_matches, parse_matches, MatchRecord, ParseError};
+use flate2::read::GzDecoder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Reported every time a log file of the import was parsed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportProgress {
+    pub path: String,
+    /// the number of parsed log files, including this one
+    pub done: usize,
+    pub total: usize,
+}
+
+/// A log file that could not be imported, the other files are imported anyway
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FileImportError {
+    pub path: String,
+    pub error: ParseError,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    /// the number of log files in the folder
+    pub files: usize,
+    /// the number of played matches in all log files
+    pub matches_found: usize,
+    pub imported: usize,
+    /// matches that were already stored or found in more than one log file
+    pub duplicates: usize,
+    pub errors: Vec<FileImportError>,
+}
+
+/// Error that stops the whole import
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ImportError {
+    /// the folder could not be read
+    Io(String),
+    History(MatchHistoryError),
+}
+
+impl From<MatchHistoryError> for ImportError {
+    fn from(error: MatchHistoryError) -> Self {
+        ImportError::History(error)
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(message) => write!(f, "could not read the log folder: {}", message),
+            ImportError::History(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// the log files in the folder, plain `.log` files and `.log.gz` archives
+pub fn log_files_in(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, ImportError> {
+    let dir = dir.as_ref();
+    let entries = fs::read_dir(dir)
+        .map_err(|error| ImportError::Io(format!("{}: {}", dir.display(), error)))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str());
+            path.is_file()
+                && name.is_some_and(|name| name.ends_with(".log") || name.ends_with(".log.gz"))
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// parses every match of a plain or gzip compressed log file
+pub fn parse_log_file_matches(path: &Path) -> Result<Vec<MatchRecord>, ParseError> {
+    let source = path.display().to_string();
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        let file = File::open(path).map_err(|error| ParseError::from_open_error(&source, error))?;
+        // The time of the archive is not the time of the session, so only the header dates it
+        parse_matches(BufReader::new(GzDecoder::new(file)), &source, None)
+    } else {
+        parse_all_matches(source)
+    }
+}
+
+/// parses the log files in parallel, `on_progress` is called from the parsing threads
+pub fn parse_log_files<F>(
+    paths: &[PathBuf],
+    on_progress: F,
+) -> Vec<Result<Vec<MatchRecord>, FileImportError>>
+where
+    F: Fn(ImportProgress) + Sync,
+{
+    let done = AtomicUsize::new(0);
+    paths
+        .par_iter()
+        .map(|path| {
+            let result = parse_log_file_matches(path).map_err(|error| FileImportError {
+                path: path.display().to_string(),
+                error,
+            });
+            on_progress(ImportProgress {
+                path: path.display().to_string(),
+                done: done.fetch_add(1, Ordering::SeqCst) + 1,
+                total: paths.len(),
+            });
+            result
+        })
+        .collect()
+}
+
+/// The played matches of the log files in a folder, not stored yet
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedImport {
+    pub report: ImportReport,
+    /// every match once, even when it was found in more than one log file
+    pub matches: Vec<HistoryMatch>,
+}
+
+impl ParsedImport {
+    /// stores the matches that are not stored yet and completes the report
+    pub fn store(self, history: &mut MatchHistory) -> Result<ImportReport, ImportError> {
+        let mut report = self.report;
+        for history_match in &self.matches {
+            if history.contains(&history_match.match_id)? {
+                report.duplicates += 1;
+                continue;
+            }
+            history.insert(history_match)?;
+            report.imported += 1;
+        }
+        Ok(report)
+    }
+}
+
+/// parses every played match of the log files in the folder,
+/// a log file that can not be parsed is reported and skipped
+pub fn parse_logs<F>(dir: impl AsRef<Path>, on_progress: F) -> Result<ParsedImport, ImportError>
+where
+    F: Fn(ImportProgress) + Sync,
+{
+    let paths = log_files_in(dir)?;
+    let mut report = ImportReport {
+        files: paths.len(),
+        ..ImportReport::default()
+    };
+    let mut matches = Vec::new();
+    let mut seen = HashSet::new();
+    for result in parse_log_files(&paths, on_progress) {
+        let records = match result {
+            Ok(records) => records,
+            Err(error) => {
+                report.errors.push(error);
+                continue;
+            }
+        };
+        // Matches cancelled while loading were never played
+        for record in records.iter().filter(|record| record.started) {
+            report.matches_found += 1;
+            if !seen.insert(record.match_id.clone()) {
+                report.duplicates += 1;
+                continue;
+            }
+            matches.push(HistoryMatch::from_match_record(record));
+        }
+    }
+    Ok(ParsedImport { report, matches })
+}
+
+/// stores every played match of the log files in the folder that is not stored yet,
+/// see [`parse_logs`] to parse the folder without the match history
+pub fn import_logs<F>(
+    dir: impl AsRef<Path>,
+    history: &mut MatchHistory,
+    on_progress: F,
+) -> Result<ImportReport, ImportError>
+where
+    F: Fn(ImportProgress) + Sync,
+{
+    parse_logs(dir, on_progress)?.store(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::import_logs;
+    use crate::match_history::{HistoryFilter, HistoryMatch, MatchHistory};
+    use crate::parse_log_file::{parse_all_matches, ParseError};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    const FIRST_SESSION: &str = r"RelicCoH3 started at 2023-05-10 18:20
+(I) [18:20:03.000] [000012345]: GAME -- Current Steam name is [Tester]
+(I) [18:25:00.000] [000012345]: GAME -- Scenario: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+(I) [18:25:00.200] [000012345]: GAME -- Human Player: 0 Tester 1234567 0 americans
+(I) [18:25:00.300] [000012345]: GAME -- Human Player: 1 Opponent 7654321 1 german
+(I) [18:25:10.500] [000012345]: GAME -- Starting mission: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+(I) [18:45:00.000] [000012345]: MOD -- Game Over at frame 9600
+";
+
+    const SECOND_SESSION: &str = r"RelicCoH3 started at 2023-05-11 09:05
+(I) [09:05:03.000] [000012345]: GAME -- Current Steam name is [Tester]
+(I) [09:10:00.000] [000012345]: GAME -- Scenario: data:scenarios\multiplayer\desert_village_2p_mkiii\desert_village_2p_mkiii
+(I) [09:10:00.200] [000012345]: GAME -- Human Player: 0 Tester 1234567 0 british
+(I) [09:10:00.300] [000012345]: GAME -- Human Player: 1 Opponent 7654321 1 german
+(I) [09:10:10.500] [000012345]: GAME -- Starting mission: data:scenarios\multiplayer\desert_village_2p_mkiii\desert_village_2p_mkiii
+(I) [09:20:00.000] [000012345]: GAME -- Scenario: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+";
+
+    #[test]
+    fn test_import_logs() {
+        let dir = std::env::temp_dir().join(format!("coh3-stats-import-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let first_path = dir.join("warnings.log");
+        fs::write(&first_path, FIRST_SESSION).unwrap();
+        // The same session again as an archive, and the next session only as an archive
+        for (name, session) in [
+            ("warnings_2023-05-10_18-20.log.gz", FIRST_SESSION),
+            ("warnings_2023-05-11_09-05.log.gz", SECOND_SESSION),
+        ] {
+            let mut encoder = GzEncoder::new(
+                File::create(dir.join(name)).unwrap(),
+                Compression::default(),
+            );
+            encoder.write_all(session.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+        }
+        fs::write(dir.join("broken.log.gz"), "not compressed").unwrap();
+        fs::write(dir.join("notes.log"), "just some text\n").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        // The match that is already stored is not imported again
+        let mut history = MatchHistory::open_in_memory().unwrap();
+        let stored = parse_all_matches(first_path.display().to_string()).unwrap();
+        history
+            .insert(&HistoryMatch::from_match_record(&stored[0]))
+            .unwrap();
+
+        let progress = Mutex::new(Vec::new());
+        let report = import_logs(&dir, &mut history, |update| {
+            progress.lock().unwrap().push(update.done)
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(report.files, 5);
+        assert_eq!(report.matches_found, 3);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.errors[0].path.ends_with("broken.log.gz"));
+        assert!(matches!(report.errors[0].error, ParseError::Io(_)));
+        assert!(report.errors[1].path.ends_with("notes.log"));
+        assert!(matches!(report.errors[1].error, ParseError::NotACoh3Log(_)));
+        let mut progress = progress.into_inner().unwrap();
+        progress.sort();
+        assert_eq!(progress, [1, 2, 3, 4, 5]);
+
+        let page = history.query(&HistoryFilter::default()).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.matches[0].map, "desert_village_2p_mkiii");
+        assert_eq!(page.matches[0].player_name, "Tester");
+        assert_eq!(page.matches[0].duration, 0);
+        assert!(page.matches[0].started_at > page.matches[1].started_at);
+    }
+}
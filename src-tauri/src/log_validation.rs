@@ -0,0 +1,244 @@
+use crate::game_process::{assess_game_state, ProcessProbe};
+use crate::parse_log_file::{is_log_line, parse_reader, GameState};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A file is recognised by its header or by a log line within the first lines
+const HEAD_LINES: usize = 200;
+
+/// Why a path can not be used as the log file. Only `NotFound`, `NotAFile` and `NotReadable`
+/// rule it out, the others are warnings as the game may still write to the file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LogFileProblem {
+    NotFound,
+    /// the path is a directory or another kind of file
+    NotAFile,
+    /// the file could not be opened or read, with the error of the system
+    NotReadable(String),
+    Empty,
+    /// the log of another Relic game, with the name from the header like `CoH2`
+    OtherGame(String),
+    /// the file has no line in the warnings.log format
+    NotACoh3Log,
+}
+
+/// Everything known about a log file path, see [`validate_log_file`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogFileReport {
+    pub path: String,
+    pub exists: bool,
+    /// the path is a regular file
+    pub is_file: bool,
+    pub readable: bool,
+    /// size in bytes
+    pub size: Option<u64>,
+    /// unix timestamp in seconds of the last modification
+    pub modified: Option<u64>,
+    /// the first lines look like a CoH3 warnings.log
+    pub is_coh3_log: bool,
+    /// the game did not log that it was closed and its process was not found gone,
+    /// see [`assess_game_state`]
+    pub game_running: bool,
+    /// `None` when the file looks like a CoH3 warnings.log
+    pub problem: Option<LogFileProblem>,
+}
+
+/// checks everything that makes the path usable as the log file and why not,
+/// the probe tells if the game that writes the log is still running
+pub fn validate_log_file(path: impl AsRef<Path>, probe: &mut dyn ProcessProbe) -> LogFileReport {
+    let path = path.as_ref();
+    let mut report = LogFileReport {
+        path: path.display().to_string(),
+        exists: false,
+        is_file: false,
+        readable: false,
+        size: None,
+        modified: None,
+        is_coh3_log: false,
+        game_running: false,
+        problem: None,
+    };
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            report.problem = Some(match error.kind() {
+                io::ErrorKind::NotFound => LogFileProblem::NotFound,
+                _ => LogFileProblem::NotReadable(error.to_string()),
+            });
+            return report;
+        }
+    };
+    report.exists = true;
+    report.is_file = metadata.is_file();
+    if !report.is_file {
+        report.problem = Some(LogFileProblem::NotAFile);
+        return report;
+    }
+    report.size = Some(metadata.len());
+    report.modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs());
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) => {
+            report.problem = Some(LogFileProblem::NotReadable(error.to_string()));
+            return report;
+        }
+    };
+    let head = match read_head(&mut file) {
+        Ok(head) => head,
+        Err(error) => {
+            report.problem = Some(LogFileProblem::NotReadable(error.to_string()));
+            return report;
+        }
+    };
+    report.readable = true;
+    if head.is_empty() {
+        report.problem = Some(LogFileProblem::Empty);
+        return report;
+    }
+
+    // Relic games start the log with a header like `RelicCoH3 started at 2023-05-10 18:20`
+    let header_game = head[0]
+        .strip_prefix("Relic")
+        .and_then(|tail| tail.split_once(" started at "))
+        .map(|(game, _)| game.to_string());
+    match header_game {
+        Some(game) if game != "CoH3" => {
+            report.problem = Some(LogFileProblem::OtherGame(game));
+            return report;
+        }
+        Some(_) => report.is_coh3_log = true,
+        None => report.is_coh3_log = head.iter().any(|line| is_log_line(line)),
+    }
+    if !report.is_coh3_log {
+        report.problem = Some(LogFileProblem::NotACoh3Log);
+        return report;
+    }
+
+    // The parser reads the log from its end, the same way the log watcher does
+    report.game_running = parse_reader(&mut file).is_ok_and(|data| {
+        let last_activity = metadata.modified().ok();
+        let state = assess_game_state(&data.game_state, last_activity, SystemTime::now(), probe);
+        matches!(
+            state,
+            GameState::Menu | GameState::Loading | GameState::InGame
+        )
+    });
+    report
+}
+
+fn read_head(file: &mut File) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    while lines.len() < HEAD_LINES {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&line);
+        lines.push(text.trim_end_matches(['\r', '\n']).to_string());
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_log_file, LogFileProblem};
+    use crate::game_process::ProcessProbe;
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+
+    struct MockProbe(Option<bool>);
+
+    impl ProcessProbe for MockProbe {
+        fn is_game_running(&mut self) -> Option<bool> {
+            self.0
+        }
+    }
+
+    const LOG: &str = r"RelicCoH3 started at 2023-05-10 18:20
+(I) [18:20:01.234] [000012345]: GAME -- [Company of Heroes 3] set to language [en]
+";
+
+    fn validate(name: &str, content: &str) -> super::LogFileReport {
+        validate_with(name, content, Some(true), 0)
+    }
+
+    // `inactive_secs` is how long ago the game last wrote the log file
+    fn validate_with(
+        name: &str,
+        content: &str,
+        running: Option<bool>,
+        inactive_secs: u64,
+    ) -> super::LogFileReport {
+        let path = std::env::temp_dir().join(format!(
+            "coh3-stats-validate-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, content).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(inactive_secs))
+            .unwrap();
+        let report = validate_log_file(&path, &mut MockProbe(running));
+        fs::remove_file(&path).ok();
+        report
+    }
+
+    #[test]
+    fn test_validate_log_file() {
+        let report = validate("running", LOG);
+        assert_eq!(report.problem, None);
+        assert!(report.exists && report.is_file && report.readable && report.is_coh3_log);
+        assert_eq!(report.size, Some(LOG.len() as u64));
+        assert!(report.modified.is_some());
+        assert!(report.game_running);
+
+        let closed = format!("{}Application closed without errors\n", LOG);
+        assert!(!validate("closed", &closed).game_running);
+        // The game may still be writing its last lines when the process is gone
+        assert!(validate_with("exiting", LOG, Some(false), 0).game_running);
+        assert!(!validate_with("crashed", LOG, Some(false), 60).game_running);
+        // Without a process check only a log written recently counts as running
+        assert!(validate_with("unchecked", LOG, None, 60).game_running);
+        assert!(!validate_with("stale", LOG, None, 3600).game_running);
+
+        // Logs without the header are recognised by their lines
+        let without_header = LOG.split_once('\n').unwrap().1;
+        assert_eq!(validate("no-header", without_header).problem, None);
+    }
+
+    #[test]
+    fn test_validate_log_file_problems() {
+        let missing = std::env::temp_dir().join("coh3-stats-validate-missing.log");
+        assert_eq!(
+            validate_log_file(&missing, &mut MockProbe(Some(true))).problem,
+            Some(LogFileProblem::NotFound)
+        );
+        let report = validate_log_file(std::env::temp_dir(), &mut MockProbe(Some(true)));
+        assert!(report.exists && !report.is_file);
+        assert_eq!(report.problem, Some(LogFileProblem::NotAFile));
+
+        assert_eq!(validate("empty", "").problem, Some(LogFileProblem::Empty));
+        assert_eq!(
+            validate("coh2", &LOG.replace("CoH3", "CoH2")).problem,
+            Some(LogFileProblem::OtherGame("CoH2".to_string()))
+        );
+        assert_eq!(
+            validate("text", "just some\ntext\n").problem,
+            Some(LogFileProblem::NotACoh3Log)
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate::game_process::{self, CrashReport, SysinfoProbe};
+#[cfg(feature = "archive")]
+use crate::log_archive::LogArchiver;
+use crate::parse_log_file::{GameState, LogFileData, LogTailer, ParseError};
+use log::{info, warn};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+// The game does not always flush the log file in a way that triggers a file system event,
+// so the log file is also checked at this interval when no event arrives
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum GameEvent {
+    /// the parsed log file data changed in any way
+    GameStateChanged(LogFileData),
+    /// a new match is loading
+    MatchFound(LogFileData),
+    /// the loading screen of the match is over and the match is running
+    MatchStarted(LogFileData),
+    /// the running match is over
+    MatchEnded(LogFileData),
+    /// the game is gone without logging that it was closed
+    GameCrashed(CrashReport),
+    /// the log file could not be parsed
+    LogFileError(ParseError),
+}
+
+impl GameEvent {
+    /// the name the event is emitted with to the frontend
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameEvent::GameStateChanged(_) => "game-state-changed",
+            GameEvent::MatchFound(_) => "match-found",
+            GameEvent::MatchStarted(_) => "match-started",
+            GameEvent::MatchEnded(_) => "match-ended",
+            GameEvent::GameCrashed(_) => "game-crashed",
+            GameEvent::LogFileError(_) => "log-file-error",
+        }
+    }
+}
+
+enum WatcherMessage {
+    FileChanged,
+    Stop,
+}
+
+/// Watches a log file on a background thread and reports every change as [`GameEvent`]s.
+/// The thread stops when the watcher is dropped, dropping waits for it, so a new watcher
+/// never runs alongside the old one and both archive the same log file.
+pub struct LogWatcher {
+    path: PathBuf,
+    sender: Sender<WatcherMessage>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LogWatcher {
+    pub fn spawn<F>(path: impl Into<PathBuf>, on_event: F) -> Self
+    where
+        F: FnMut(GameEvent) + Send + 'static,
+    {
+        Self::spawn_with(path, || {}, on_event)
+    }
+
+    /// like [`spawn`](LogWatcher::spawn), but the archiver copies the log file before it is parsed,
+    /// so the game can not overwrite a session that was not archived
+    #[cfg(feature = "archive")]
+    pub fn spawn_archiving<F>(
+        path: impl Into<PathBuf>,
+        mut archiver: LogArchiver,
+        on_event: F,
+    ) -> Self
+    where
+        F: FnMut(GameEvent) + Send + 'static,
+    {
+        let archive = move || match archiver.poll() {
+            Ok(Some(archive)) => info!("Archived the previous session to {}", archive.display()),
+            Ok(None) => {}
+            Err(error) => warn!(
+                "Could not archive the log file to {}: {}",
+                archiver.archive_dir().display(),
+                error
+            ),
+        };
+        Self::spawn_with(path, archive, on_event)
+    }
+
+    // `before_poll` runs on the watcher thread every time the log file is checked
+    fn spawn_with<B, F>(path: impl Into<PathBuf>, mut before_poll: B, mut on_event: F) -> Self
+    where
+        B: FnMut() + Send + 'static,
+        F: FnMut(GameEvent) + Send + 'static,
+    {
+        let path = path.into();
+        let (sender, receiver) = mpsc::channel();
+
+        // The game replaces the log file on launch, so the folder is watched instead of the file
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let event_sender = sender.clone();
+        let file_watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == file_name.as_deref())
+                    {
+                        event_sender.send(WatcherMessage::FileChanged).ok();
+                    }
+                }
+            })
+            .and_then(|mut file_watcher| {
+                if let Some(folder) = path.parent() {
+                    file_watcher.watch(folder, RecursiveMode::NonRecursive)?;
+                }
+                Ok(file_watcher)
+            });
+        let file_watcher = match file_watcher {
+            Ok(file_watcher) => Some(file_watcher),
+            Err(error) => {
+                warn!(
+                    "Could not watch {}, falling back to polling: {}",
+                    path.display(),
+                    error
+                );
+                None
+            }
+        };
+
+        let thread_path = path.clone();
+        let thread = thread::spawn(move || {
+            // Keep the file watcher alive as long as the thread runs
+            let _file_watcher = file_watcher;
+            let mut tailer = LogTailer::new(&thread_path);
+            let mut probe = SysinfoProbe::new();
+            let mut last_data: Option<LogFileData> = None;
+            let mut last_error: Option<ParseError> = None;
+            info!("Watching log file {}", thread_path.display());
+
+            loop {
+                before_poll();
+                match tailer.poll() {
+                    Ok(mut data) => {
+                        last_error = None;
+                        // The log file alone does not tell when the game crashed
+                        let last_activity = fs::metadata(&thread_path)
+                            .and_then(|metadata| metadata.modified())
+                            .ok();
+                        data.game_state = game_process::assess_game_state(
+                            &data.game_state,
+                            last_activity,
+                            SystemTime::now(),
+                            &mut probe,
+                        );
+                        let crashed = data.game_state == GameState::Crashed
+                            && last_data.as_ref().map(|data| &data.game_state)
+                                != Some(&GameState::Crashed);
+                        for event in game_events(last_data.as_ref(), &data) {
+                            on_event(event);
+                        }
+                        if crashed {
+                            let report = game_process::crash_report(&thread_path, last_activity);
+                            on_event(GameEvent::GameCrashed(report));
+                        }
+                        last_data = Some(data);
+                    }
+                    Err(error) => {
+                        if last_error.as_ref() != Some(&error) {
+                            last_error = Some(error.clone());
+                            on_event(GameEvent::LogFileError(error));
+                        }
+                    }
+                }
+
+                // Wait for the next change, a burst of writes only needs to be parsed once
+                let mut messages = vec![receiver.recv_timeout(FALLBACK_POLL_INTERVAL)];
+                messages.extend(receiver.try_iter().map(Ok));
+                if messages.iter().any(|message| {
+                    matches!(
+                        message,
+                        Ok(WatcherMessage::Stop) | Err(RecvTimeoutError::Disconnected)
+                    )
+                }) {
+                    break;
+                }
+            }
+            info!("Stopped watching log file {}", thread_path.display());
+        });
+
+        LogWatcher {
+            path,
+            sender,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LogWatcher {
+    fn drop(&mut self) {
+        self.sender.send(WatcherMessage::Stop).ok();
+        if let Some(thread) = self.thread.take() {
+            // A watcher dropped by its own event handler can not wait for itself
+            if thread.thread().id() != thread::current().id() {
+                thread.join().ok();
+            }
+        }
+    }
+}
+
+/// compares two consecutive parse results and returns the events that lead from one to the other
+pub fn game_events(previous: Option<&LogFileData>, current: &LogFileData) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    if previous == Some(current) {
+        return events;
+    }
+    events.push(GameEvent::GameStateChanged(current.clone()));
+
+    let previous_state = previous.map(|data| &data.game_state);
+    let entered = |state: GameState| previous_state != Some(&state) && current.game_state == state;
+    if entered(GameState::Loading) {
+        events.push(GameEvent::MatchFound(current.clone()));
+    }
+    if entered(GameState::InGame) {
+        events.push(GameEvent::MatchStarted(current.clone()));
+    }
+    if previous_state == Some(&GameState::InGame) && current.game_state != GameState::InGame {
+        events.push(GameEvent::MatchEnded(current.clone()));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{game_events, GameEvent, LogWatcher};
+    use crate::parse_log_file::{GameState, GameType, LogFileData, TeamData, TeamSide};
+    use std::sync::Arc;
+
+    fn log_file_data(game_state: GameState) -> LogFileData {
+        let team = TeamData {
+            players: Vec::new(),
+            side: TeamSide::Mixed,
+        };
+        LogFileData {
+            match_id: "".to_string(),
+            game_state,
+            game_type: GameType::Custom,
+            timestamp: "".to_string(),
+            started_at: None,
+            ended_at: None,
+            duration: 0,
+            map: "".to_string(),
+            win_condition: "".to_string(),
+            left: team.clone(),
+            right: team,
+            player_name: "".to_string(),
+            player_steam_id: "".to_string(),
+            language_code: "".to_string(),
+        }
+    }
+
+    fn event_names(events: Vec<GameEvent>) -> Vec<&'static str> {
+        events.iter().map(|event| event.name()).collect()
+    }
+
+    #[test]
+    fn test_game_events_unchanged() {
+        let data = log_file_data(GameState::Menu);
+        assert!(game_events(Some(&data), &data).is_empty());
+    }
+
+    #[test]
+    fn test_game_events_first_parse() {
+        let data = log_file_data(GameState::Menu);
+        assert_eq!(
+            event_names(game_events(None, &data)),
+            ["game-state-changed"]
+        );
+    }
+
+    #[test]
+    fn test_game_events_match_lifecycle() {
+        let menu = log_file_data(GameState::Menu);
+        let loading = log_file_data(GameState::Loading);
+        let in_game = log_file_data(GameState::InGame);
+        let closed = log_file_data(GameState::Closed);
+        assert_eq!(
+            event_names(game_events(Some(&menu), &loading)),
+            ["game-state-changed", "match-found"]
+        );
+        assert_eq!(
+            event_names(game_events(Some(&loading), &in_game)),
+            ["game-state-changed", "match-started"]
+        );
+        assert_eq!(
+            event_names(game_events(Some(&in_game), &menu)),
+            ["game-state-changed", "match-ended"]
+        );
+        assert_eq!(
+            event_names(game_events(Some(&in_game), &closed)),
+            ["game-state-changed", "match-ended"]
+        );
+    }
+
+    #[test]
+    fn test_log_watcher_drop_waits_for_thread() {
+        let path =
+            std::env::temp_dir().join(format!("coh3-stats-watcher-{}.log", std::process::id()));
+        let handler_state = Arc::new(());
+        let thread_state = handler_state.clone();
+        let watcher = LogWatcher::spawn(&path, move |_| {
+            let _ = &thread_state;
+        });
+        drop(watcher);
+        // The event handler was dropped with the stopped thread
+        assert_eq!(Arc::strong_count(&handler_state), 1);
+    }
+}
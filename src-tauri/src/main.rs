@@ -4,8 +4,20 @@
 )]
 
 extern crate machine_uid;
-use coh3_stats_desktop_app::parse_log_file;
+use coh3_stats_desktop_app::commands::{self, LogTailerState, MatchHistoryState};
+use coh3_stats_desktop_app::log_archive::{ArchiveConfig, LogArchiver};
+use coh3_stats_desktop_app::log_discovery;
+use coh3_stats_desktop_app::log_watcher::{GameEvent, LogWatcher};
+use coh3_stats_desktop_app::match_history::{HistoryMatch, MatchHistory};
+use coh3_stats_desktop_app::overlay_server::{self, OverlayServerState};
+use coh3_stats_desktop_app::parse_log_file::LogFileData;
+use coh3_stats_desktop_app::relic_api::{fill_players, PlayerStats, RelicApiClient};
+use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::Manager;
 use tauri_plugin_log::LogTarget;
 use window_shadows::set_shadow;
@@ -16,14 +28,36 @@ struct Payload {
     cwd: String,
 }
 
+/// The log watcher emitting game events to the frontend, replaced when the log file path changes
+#[derive(Default)]
+struct LogWatcherState(Mutex<Option<LogWatcher>>);
+
 fn main() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             get_default_log_file_path,
+            commands::discover_log_files,
             check_log_file_exists,
+            commands::validate_log_file,
             get_machine_id,
-            parse_log_file::parse_log_file_reverse
+            watch_log_file,
+            commands::parse_log_file_reverse,
+            commands::parse_all_matches,
+            commands::parse_log_file_tail,
+            commands::get_recent_game_errors,
+            commands::parse_match_start_message,
+            commands::get_match_history,
+            commands::import_logs,
+            commands::enrich_log_file_data,
+            commands::get_enriched_players,
+            commands::set_overlay_server,
+            commands::set_overlay_html
         ])
+        .manage(LogTailerState::default())
+        .manage(LogWatcherState::default())
+        .manage(MatchHistoryState::default())
+        .manage(OverlayServerState::default())
+        .manage(RelicApiClient::default())
         .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
             let window = app.get_window("main").unwrap();
             window.set_focus().ok();
@@ -48,22 +82,225 @@ fn main() {
             // Add window shadows
             let window = app.get_window("main").unwrap();
             set_shadow(&window, true).expect("Unsupported platform!");
+
+            // Open the match history before the watcher can report the first match
+            match open_match_history(app) {
+                Ok(history) => *app.state::<MatchHistoryState>().0.lock().unwrap() = Some(history),
+                Err(error) => log::error!("Could not open the match history: {}", error),
+            }
+
+            // The overlay server is opt-in
+            let config = read_config(&app.handle());
+            let overlay_server_enabled = config
+                .as_ref()
+                .and_then(|config| config.get("overlayServerEnabled")?.as_bool())
+                .unwrap_or(false);
+            if overlay_server_enabled {
+                let port = config
+                    .as_ref()
+                    .and_then(|config| config.get("overlayServerPort")?.as_u64())
+                    .and_then(|port| u16::try_from(port).ok())
+                    .unwrap_or(overlay_server::DEFAULT_PORT);
+                if let Err(error) = app.state::<OverlayServerState>().configure(true, port) {
+                    log::error!(
+                        "Could not start the overlay server on port {}: {}",
+                        port,
+                        error
+                    );
+                }
+            }
+
+            // Start watching the configured log file right away
+            let path = get_configured_log_file_path(app);
+            let watcher = spawn_log_watcher(app.handle(), path);
+            *app.state::<LogWatcherState>().0.lock().unwrap() = Some(watcher);
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
-/// returns the default expected log file path
+/// returns the most recently modified log file that was found,
+/// or the expected path in the Documents folder if there is none
 #[tauri::command]
 fn get_default_log_file_path() -> String {
-    let mut path = tauri::api::path::document_dir().unwrap();
+    if let Some(candidate) = log_discovery::discover_log_files().into_iter().next() {
+        return candidate.path;
+    }
+    let mut path = tauri::api::path::document_dir().unwrap_or_default();
     path.push("My Games");
     path.push("Company of Heroes 3");
     path.push("warnings.log");
     path.display().to_string()
 }
 
+/// reads the settings the frontend stores in the app data dir
+fn read_config(app: &tauri::AppHandle) -> Option<serde_json::Value> {
+    app.path_resolver()
+        .app_data_dir()
+        .and_then(|app_data_dir| fs::read_to_string(app_data_dir.join("config.dat")).ok())
+        .and_then(|config| serde_json::from_str::<serde_json::Value>(&config).ok())
+}
+
+/// returns the log file path configured in the settings or the default path
+fn get_configured_log_file_path(app: &tauri::App) -> String {
+    read_config(&app.handle())
+        .and_then(|config| config.get("logFilePath")?.as_str().map(str::to_string))
+        .unwrap_or_else(get_default_log_file_path)
+}
+
+/// opens the match history database in the app data dir
+fn open_match_history(app: &tauri::App) -> Result<MatchHistory, Box<dyn std::error::Error>> {
+    let app_data_dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or("app data dir not found")?;
+    fs::create_dir_all(&app_data_dir)?;
+    Ok(MatchHistory::open(
+        app_data_dir.join("match_history.sqlite"),
+    )?)
+}
+
+/// creates the archiver keeping the sessions of the log file in the app data dir,
+/// the limits can be changed in the settings
+fn create_log_archiver(app: &tauri::AppHandle, path: &str) -> Option<LogArchiver> {
+    let archive_dir = app.path_resolver().app_data_dir()?.join("log_archive");
+    let config = read_config(app);
+    let defaults = ArchiveConfig::default();
+    let archive_config = ArchiveConfig {
+        max_files: config
+            .as_ref()
+            .and_then(|config| config.get("logArchiveMaxFiles")?.as_u64())
+            .map(|max_files| max_files as usize)
+            .unwrap_or(defaults.max_files),
+        max_bytes: config
+            .as_ref()
+            .and_then(|config| config.get("logArchiveMaxMegabytes")?.as_u64())
+            .map(|megabytes| megabytes * 1024 * 1024)
+            .unwrap_or(defaults.max_bytes),
+    };
+    Some(LogArchiver::new(path, archive_dir, archive_config))
+}
+
+/// The stats of the players of the current match, requested once per match
+#[derive(Default)]
+struct MatchEnrichment {
+    /// the match the stats are requested or were received for
+    match_id: String,
+    stats: Option<HashMap<u64, PlayerStats>>,
+    /// the last data emitted to the frontend, emitted again once the stats arrived
+    latest: Option<LogFileData>,
+}
+
+/// starts a log watcher that emits every game event to the frontend and the overlay server
+/// and stores every ended match in the match history,
+/// the players of each new match are enriched from the Relic API on another thread
+fn spawn_log_watcher(app: tauri::AppHandle, path: String) -> LogWatcher {
+    let archiver = create_log_archiver(&app, &path);
+    let enrichment = Arc::new(Mutex::new(MatchEnrichment::default()));
+    let on_event = move |mut event: GameEvent| {
+        // Held until the event is emitted, so the enriched data is never overtaken by older data
+        let mut current = enrichment.lock().unwrap();
+        if let GameEvent::GameStateChanged(data)
+        | GameEvent::MatchFound(data)
+        | GameEvent::MatchStarted(data)
+        | GameEvent::MatchEnded(data) = &mut event
+        {
+            if data.match_id != current.match_id {
+                current.match_id = data.match_id.clone();
+                current.stats = None;
+                if !data.match_id.is_empty() {
+                    spawn_enrichment(app.clone(), enrichment.clone(), data.clone());
+                }
+            }
+            if let Some(stats) = &current.stats {
+                fill_players(data, stats);
+            }
+            current.latest = Some(data.clone());
+        }
+        match &event {
+            GameEvent::GameStateChanged(data) => app
+                .state::<OverlayServerState>()
+                .content
+                .set_game(data.clone()),
+            GameEvent::MatchEnded(data) => record_match(&app, data),
+            _ => {}
+        }
+        app.emit_all(event.name(), &event).ok();
+    };
+    match archiver {
+        Some(archiver) => LogWatcher::spawn_archiving(path, archiver, on_event),
+        None => LogWatcher::spawn(path, on_event),
+    }
+}
+
+/// requests the stats of the players of the match and emits the latest data with them
+fn spawn_enrichment(
+    app: tauri::AppHandle,
+    enrichment: Arc<Mutex<MatchEnrichment>>,
+    data: LogFileData,
+) {
+    thread::spawn(move || {
+        let result = app.state::<RelicApiClient>().get_match_stats(&data);
+        let mut current = enrichment.lock().unwrap();
+        // Another match was found while the stats were requested
+        if current.match_id != data.match_id {
+            return;
+        }
+        let stats = match result {
+            Ok(stats) => stats,
+            Err(error) => {
+                // The players are sent without their stats, the next event tries again
+                log::warn!("Could not enrich the players: {}", error);
+                current.match_id.clear();
+                return;
+            }
+        };
+        if let Some(mut latest) = current.latest.clone() {
+            fill_players(&mut latest, &stats);
+            app.state::<OverlayServerState>()
+                .content
+                .set_game(latest.clone());
+            let event = GameEvent::GameStateChanged(latest);
+            app.emit_all(event.name(), &event).ok();
+        }
+        current.stats = Some(stats);
+    });
+}
+
+/// stores the last match of the log file data in the match history
+fn record_match(app: &tauri::AppHandle, data: &LogFileData) {
+    if data.map.is_empty() {
+        return;
+    }
+    // Only used when the log does not date the match
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let history_match = HistoryMatch::from_log_file_data(data, now);
+    let history = app.state::<MatchHistoryState>();
+    let mut history = history.0.lock().unwrap();
+    if let Some(history) = history.as_mut() {
+        if let Err(error) = history.insert(&history_match) {
+            log::error!(
+                "Could not store match {}: {}",
+                history_match.match_id,
+                error
+            );
+        }
+    }
+}
+
+/// starts watching the given log file instead of the currently watched one
+#[tauri::command]
+fn watch_log_file(path: String, app: tauri::AppHandle, watcher: tauri::State<LogWatcherState>) {
+    let mut watcher = watcher.0.lock().unwrap();
+    // Drop the old watcher first, it waits until the old thread stopped archiving and emitting
+    *watcher = None;
+    *watcher = Some(spawn_log_watcher(app, path));
+}
+
 /// checks if log file can be found on system
 #[tauri::command]
 fn check_log_file_exists(path: &str) -> bool {
@@ -0,0 +1,643 @@
+use crate::leaderboards::GameMode;
+use crate::parse_log_file::{
+    get_team_data, Faction, GameType, LogFileData, MatchRecord, PlayerData, PlayerProfile, TeamData,
+};
+use chrono::DateTime;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS matches (
+    match_id TEXT PRIMARY KEY,
+    map TEXT NOT NULL,
+    win_condition TEXT NOT NULL,
+    game_type TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    log_timestamp TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    player_name TEXT NOT NULL,
+    player_steam_id TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS matches_started_at ON matches (started_at);
+CREATE TABLE IF NOT EXISTS match_players (
+    match_id TEXT NOT NULL,
+    side INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    ai INTEGER NOT NULL,
+    faction TEXT NOT NULL,
+    relic_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    steam_id TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    is_local INTEGER NOT NULL,
+    PRIMARY KEY (match_id, side, position)
+);
+";
+
+/// A match as it is stored in the history
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryMatch {
+    /// stays the same when the same match is detected again
+    pub match_id: String,
+    pub map: String,
+    pub win_condition: String,
+    pub game_type: GameType,
+    /// 1v1 to 4v4, derived from the teams
+    pub mode: GameMode,
+    /// unix timestamp in seconds
+    pub started_at: u64,
+    /// timestamp in the log file when the match started
+    pub log_timestamp: String,
+    /// duration in seconds
+    pub duration: u64,
+    pub left: TeamData,
+    pub right: TeamData,
+    pub player_name: String,
+    pub player_steam_id: String,
+}
+
+impl HistoryMatch {
+    /// builds the history entry of the last match in the log file data, the times are taken
+    /// from the log, `ended_at` is the unix timestamp in seconds used when the log does not date it
+    pub fn from_log_file_data(data: &LogFileData, ended_at: u64) -> Self {
+        let started_at = log_started_at(&data.started_at, &data.ended_at, data.duration)
+            .unwrap_or(ended_at.saturating_sub(data.duration));
+        HistoryMatch {
+            match_id: data.match_id.clone(),
+            map: data.map.clone(),
+            win_condition: data.win_condition.clone(),
+            game_type: data.game_type.clone(),
+            mode: GameMode::from_teams(&data.left, &data.right),
+            started_at,
+            log_timestamp: data.timestamp.clone(),
+            duration: data.duration,
+            left: data.left.clone(),
+            right: data.right.clone(),
+            player_name: data.player_name.clone(),
+            player_steam_id: data.player_steam_id.clone(),
+        }
+    }
+
+    /// builds the history entry of a match parsed from a whole log file,
+    /// `started_at` is 0 when the date of the session is unknown
+    pub fn from_match_record(record: &MatchRecord) -> Self {
+        let started_at =
+            log_started_at(&record.started_at, &record.ended_at, record.duration).unwrap_or(0);
+        HistoryMatch {
+            match_id: record.match_id.clone(),
+            map: record.map.clone(),
+            win_condition: record.win_condition.clone(),
+            game_type: record.game_type.clone(),
+            mode: GameMode::from_teams(&record.left, &record.right),
+            started_at,
+            log_timestamp: record.timestamp.clone(),
+            duration: record.duration,
+            left: record.left.clone(),
+            right: record.right.clone(),
+            player_name: record.player_name.clone(),
+            player_steam_id: record.player_steam_id.clone(),
+        }
+    }
+}
+
+/// Filters and paging of a history query, every filter is optional
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct HistoryFilter {
+    pub map: Option<String>,
+    pub mode: Option<GameMode>,
+    /// the faction the player running the game played
+    pub faction: Option<Faction>,
+    /// name or relic id of a player in the opposing team
+    pub opponent: Option<String>,
+    /// unix timestamp in seconds, inclusive
+    pub from: Option<u64>,
+    /// unix timestamp in seconds, inclusive
+    pub to: Option<u64>,
+    /// zero based
+    pub page: u32,
+    /// 0 uses the default page size
+    pub page_size: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryPage {
+    /// newest match first
+    pub matches: Vec<HistoryMatch>,
+    /// number of matches matching the filter on all pages
+    pub total: u64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Error returned by the match history
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MatchHistoryError {
+    /// the database could not be opened when the app started
+    NotOpen,
+    Database(String),
+}
+
+impl From<rusqlite::Error> for MatchHistoryError {
+    fn from(error: rusqlite::Error) -> Self {
+        MatchHistoryError::Database(error.to_string())
+    }
+}
+
+impl fmt::Display for MatchHistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchHistoryError::NotOpen => write!(f, "match history database is not open"),
+            MatchHistoryError::Database(message) => {
+                write!(f, "match history database error: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MatchHistoryError {}
+
+// The steam id of the player running the game is compared once the player was enriched.
+// Without it the name is only trusted when no other human player of the match has the same name,
+// otherwise it is unknown which of them is local and none is marked
+fn is_local_player(player: &PlayerData, history_match: &HistoryMatch) -> bool {
+    if player.ai {
+        return false;
+    }
+    if !player.steam_id.is_empty() && !history_match.player_steam_id.is_empty() {
+        return player.steam_id == history_match.player_steam_id;
+    }
+    let same_name = history_match
+        .left
+        .players
+        .iter()
+        .chain(history_match.right.players.iter())
+        .filter(|other| !other.ai && other.name == player.name)
+        .count();
+    player.name == history_match.player_name && same_name == 1
+}
+
+// The unix timestamp of the start of a match from its RFC 3339 times in the log,
+// the end is used when only it is known
+fn log_started_at(
+    started_at: &Option<String>,
+    ended_at: &Option<String>,
+    duration: u64,
+) -> Option<u64> {
+    let unix_timestamp = |time: &Option<String>| {
+        time.as_deref()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .and_then(|time| u64::try_from(time.timestamp()).ok())
+    };
+    unix_timestamp(started_at)
+        .or_else(|| unix_timestamp(ended_at).map(|ended_at| ended_at.saturating_sub(duration)))
+}
+
+/// Every match that was detected, stored in a SQLite database
+pub struct MatchHistory {
+    connection: Connection,
+}
+
+impl MatchHistory {
+    /// opens the database at the given path, creates it when it does not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MatchHistoryError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, MatchHistoryError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, MatchHistoryError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(MatchHistory { connection })
+    }
+
+    /// stores the match, a match with the same id is replaced
+    pub fn insert(&mut self, history_match: &HistoryMatch) -> Result<(), MatchHistoryError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO matches (match_id, map, win_condition, game_type, mode,
+                started_at, log_timestamp, duration, player_name, player_steam_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                history_match.match_id,
+                history_match.map,
+                history_match.win_condition,
+                to_sql_name(&history_match.game_type),
+                to_sql_name(&history_match.mode),
+                history_match.started_at,
+                history_match.log_timestamp,
+                history_match.duration,
+                history_match.player_name,
+                history_match.player_steam_id,
+            ],
+        )?;
+        transaction.execute(
+            "DELETE FROM match_players WHERE match_id = ?1",
+            params![history_match.match_id],
+        )?;
+        for (side, team) in [&history_match.left, &history_match.right]
+            .into_iter()
+            .enumerate()
+        {
+            for player in &team.players {
+                transaction.execute(
+                    "INSERT INTO match_players (match_id, side, position, ai, faction, relic_id,
+                        name, steam_id, rank, is_local)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        history_match.match_id,
+                        side,
+                        player.position,
+                        player.ai,
+                        player.faction.as_str(),
+                        player.relic_id,
+                        player.name,
+                        player.steam_id,
+                        player.rank,
+                        is_local_player(player, history_match),
+                    ],
+                )?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// checks if a match with the id is stored
+    pub fn contains(&self, match_id: &str) -> Result<bool, MatchHistoryError> {
+        Ok(self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM matches WHERE match_id = ?1)",
+            params![match_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// returns one page of the matches matching the filter, newest match first
+    pub fn query(&self, filter: &HistoryFilter) -> Result<HistoryPage, MatchHistoryError> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(map) = &filter.map {
+            conditions.push("m.map = ?");
+            values.push(Value::Text(map.clone()));
+        }
+        if let Some(mode) = &filter.mode {
+            conditions.push("m.mode = ?");
+            values.push(Value::Text(to_sql_name(mode)));
+        }
+        if let Some(faction) = &filter.faction {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM match_players p
+                    WHERE p.match_id = m.match_id AND p.is_local = 1 AND p.faction = ?)",
+            );
+            values.push(Value::Text(faction.as_str().to_string()));
+        }
+        if let Some(opponent) = &filter.opponent {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM match_players o
+                    JOIN match_players l
+                        ON l.match_id = o.match_id AND l.is_local = 1 AND l.side != o.side
+                    WHERE o.match_id = m.match_id AND (o.name = ? OR o.relic_id = ?))",
+            );
+            values.push(Value::Text(opponent.clone()));
+            values.push(Value::Text(opponent.clone()));
+        }
+        if let Some(from) = filter.from {
+            conditions.push("m.started_at >= ?");
+            values.push(Value::Integer(from as i64));
+        }
+        if let Some(to) = filter.to {
+            conditions.push("m.started_at <= ?");
+            values.push(Value::Integer(to as i64));
+        }
+        let where_clause = if conditions.is_empty() {
+            "".to_string()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let total: u64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM matches m {}", where_clause),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        let page_size = if filter.page_size == 0 {
+            DEFAULT_PAGE_SIZE
+        } else {
+            filter.page_size
+        };
+        values.push(Value::Integer(page_size as i64));
+        values.push(Value::Integer(filter.page as i64 * page_size as i64));
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT m.match_id, m.map, m.win_condition, m.game_type, m.mode, m.started_at,
+                m.log_timestamp, m.duration, m.player_name, m.player_steam_id
+             FROM matches m {}
+             ORDER BY m.started_at DESC, m.match_id
+             LIMIT ? OFFSET ?",
+            where_clause
+        ))?;
+        let rows = statement.query_map(params_from_iter(values.iter()), |row| {
+            Ok(HistoryMatch {
+                match_id: row.get(0)?,
+                map: row.get(1)?,
+                win_condition: row.get(2)?,
+                game_type: from_sql_name(row.get(3)?)?,
+                mode: from_sql_name(row.get(4)?)?,
+                started_at: row.get(5)?,
+                log_timestamp: row.get(6)?,
+                duration: row.get(7)?,
+                left: get_team_data(Vec::new()),
+                right: get_team_data(Vec::new()),
+                player_name: row.get(8)?,
+                player_steam_id: row.get(9)?,
+            })
+        })?;
+
+        let mut matches = Vec::new();
+        for row in rows {
+            let mut history_match = row?;
+            let (left, right) = self.get_teams(&history_match.match_id)?;
+            history_match.left = left;
+            history_match.right = right;
+            matches.push(history_match);
+        }
+        Ok(HistoryPage {
+            matches,
+            total,
+            page: filter.page,
+            page_size,
+        })
+    }
+
+    fn get_teams(&self, match_id: &str) -> Result<(TeamData, TeamData), MatchHistoryError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT side, position, ai, faction, relic_id, name, steam_id, rank
+             FROM match_players WHERE match_id = ?1 ORDER BY side, position",
+        )?;
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let rows = statement.query_map(params![match_id], |row| {
+            let side: u8 = row.get(0)?;
+            let faction: String = row.get(3)?;
+            let player = PlayerData {
+                position: row.get(1)?,
+                ai: row.get(2)?,
+                faction: Faction::from(faction),
+                relic_id: row.get(4)?,
+                name: row.get(5)?,
+                steam_id: row.get(6)?,
+                rank: row.get(7)?,
+                profile: PlayerProfile::default(),
+            };
+            Ok((side, player))
+        })?;
+        for row in rows {
+            match row? {
+                (0, player) => left.push(player),
+                (_, player) => right.push(player),
+            }
+        }
+        Ok((get_team_data(left), get_team_data(right)))
+    }
+}
+
+// Enums are stored with the same names they are serialized with for the frontend
+fn to_sql_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "".to_string(),
+    }
+}
+
+fn from_sql_name<T: DeserializeOwned>(name: String) -> rusqlite::Result<T> {
+    serde_json::from_value(serde_json::Value::String(name)).map_err(|error| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(error))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_local_player, HistoryFilter, HistoryMatch, MatchHistory};
+    use crate::leaderboards::GameMode;
+    use crate::parse_log_file::{parse_bytes, parse_matches, Faction, LogFileData};
+    use std::io::Cursor;
+
+    const LOG_HEADER: &str = "RelicCoH3 started at 2023-05-10 18:20\n";
+
+    const LOG_PROFILE: &str = r"(I) [18:20:03.000] [000012345]: GAME -- Current Steam name is [Tester]
+(I) [18:20:04.000] [000012345]: Found profile: /steam/76561198000000001
+";
+
+    // A finished 1v1 of Tester against a human opponent playing german, started at the hour
+    fn log_game(hour: u32, map: &str, own: Faction, opponent: &str) -> String {
+        let own = own.as_str();
+        format!(
+            r"(I) [{hour}:25:00.000] [000012345]: GAME -- Scenario: data:scenarios\multiplayer\{map}\{map}
+(I) [{hour}:25:00.100] [000012345]: GAME -- Win Condition Name: VictoryPoint
+(I) [{hour}:25:00.200] [000012345]: GAME -- Human Player: 0 Tester 1234567 0 {own}
+(I) [{hour}:25:00.300] [000012345]: GAME -- Human Player: 1 {opponent} 7654321 1 german
+(I) [{hour}:25:10.000] [000012345]: GameApp::SetState : new (Game) old (LoadingGame)
+(I) [{hour}:25:10.500] [000012345]: GAME -- Starting mission: data:scenarios\multiplayer\{map}\{map}
+(I) [{hour}:45:00.000] [000012345]: MOD -- Game Over at frame 9600
+"
+        )
+    }
+
+    // The last match of an undated log, as the log watcher sees it
+    fn log_file_data(own: Faction, opponent: &str) -> LogFileData {
+        let log = [
+            LOG_PROFILE,
+            &log_game(18, "twin_beach_2p_mkii", own, opponent),
+        ]
+        .concat();
+        parse_bytes(log.as_bytes()).unwrap()
+    }
+
+    // Every match of a dated session, as the log import finds them
+    fn filled_history() -> (MatchHistory, Vec<HistoryMatch>) {
+        let log = [
+            LOG_HEADER,
+            LOG_PROFILE,
+            &log_game(18, "twin_beach_2p_mkii", Faction::Americans, "Enemy"),
+            &log_game(19, "desert_village_2p_mkiii", Faction::British, "Enemy"),
+            &log_game(20, "twin_beach_2p_mkii", Faction::British, "Other"),
+        ]
+        .concat();
+        let records = parse_matches(Cursor::new(log), "<test>", None).unwrap();
+        let mut history = MatchHistory::open_in_memory().unwrap();
+        let matches: Vec<HistoryMatch> = records
+            .iter()
+            .map(HistoryMatch::from_match_record)
+            .collect();
+        for history_match in &matches {
+            history.insert(history_match).unwrap();
+        }
+        (history, matches)
+    }
+
+    #[test]
+    fn test_match_history_roundtrip() {
+        let mut history = MatchHistory::open_in_memory().unwrap();
+        let data = log_file_data(Faction::Americans, "Enemy");
+        let history_match = HistoryMatch::from_log_file_data(&data, 1_683_740_000);
+        history.insert(&history_match).unwrap();
+        // The same match detected again replaces the stored one
+        history.insert(&history_match).unwrap();
+
+        let page = history.query(&HistoryFilter::default()).unwrap();
+        assert_eq!(page.total, 1);
+        assert!(history.contains(&history_match.match_id).unwrap());
+        assert!(!history.contains("unknown").unwrap());
+        assert_eq!(page.matches, [history_match]);
+        // The log is not dated, the match ended when it was stored
+        assert_eq!(page.matches[0].started_at, 1_683_740_000 - 1200);
+        assert_eq!(page.matches[0].mode, GameMode::OneVsOne);
+        assert_eq!(page.matches[0].player_name, "Tester");
+        assert_eq!(page.matches[0].player_steam_id, "76561198000000001");
+    }
+
+    #[test]
+    fn test_history_match_times_from_log() {
+        let log = [
+            LOG_HEADER,
+            LOG_PROFILE,
+            &log_game(18, "twin_beach_2p_mkii", Faction::Americans, "Enemy"),
+        ]
+        .concat();
+        let mut data = parse_bytes(log.as_bytes()).unwrap();
+        let unix_timestamp = |time: &Option<String>| {
+            chrono::DateTime::parse_from_rfc3339(time.as_deref().unwrap())
+                .unwrap()
+                .timestamp() as u64
+        };
+        let started_at = unix_timestamp(&data.started_at);
+        let history_match = HistoryMatch::from_log_file_data(&data, 1_700_000_000);
+        assert_eq!(history_match.started_at, started_at);
+
+        // The end is only used when the start is unknown
+        data.started_at = None;
+        let history_match = HistoryMatch::from_log_file_data(&data, 1_700_000_000);
+        assert_eq!(
+            history_match.started_at,
+            unix_timestamp(&data.ended_at) - data.duration
+        );
+    }
+
+    #[test]
+    fn test_is_local_player() {
+        let data = log_file_data(Faction::Americans, "Tester");
+        let history_match = HistoryMatch::from_log_file_data(&data, 1_683_740_000);
+        let (own, opponent) = (&data.left.players[0], &data.right.players[0]);
+        // Without steam ids the opponent with the same name can not be told apart
+        assert!(!is_local_player(own, &history_match));
+        assert!(!is_local_player(opponent, &history_match));
+
+        let mut own = own.clone();
+        own.steam_id = "76561198000000001".to_string();
+        let mut opponent = opponent.clone();
+        opponent.steam_id = "76561198000000002".to_string();
+        assert!(is_local_player(&own, &history_match));
+        assert!(!is_local_player(&opponent, &history_match));
+
+        // A unique name is enough
+        let data = log_file_data(Faction::Americans, "Enemy");
+        let history_match = HistoryMatch::from_log_file_data(&data, 1_683_740_000);
+        assert!(is_local_player(&data.left.players[0], &history_match));
+        assert!(!is_local_player(&data.right.players[0], &history_match));
+    }
+
+    #[test]
+    fn test_match_history_filters() {
+        let (history, matches) = filled_history();
+        let query = |filter: HistoryFilter| {
+            let page = history.query(&filter).unwrap();
+            page.matches
+                .iter()
+                .map(|history_match| history_match.map.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            query(HistoryFilter::default()),
+            [
+                "twin_beach_2p_mkii",
+                "desert_village_2p_mkiii",
+                "twin_beach_2p_mkii"
+            ]
+        );
+        assert_eq!(
+            history
+                .query(&HistoryFilter {
+                    map: Some("twin_beach_2p_mkii".to_string()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .matches,
+            [matches[2].clone(), matches[0].clone()]
+        );
+        assert_eq!(
+            query(HistoryFilter {
+                mode: Some(GameMode::OneVsOne),
+                ..Default::default()
+            })
+            .len(),
+            3
+        );
+        assert!(query(HistoryFilter {
+            mode: Some(GameMode::TwoVsTwo),
+            ..Default::default()
+        })
+        .is_empty());
+        assert_eq!(
+            query(HistoryFilter {
+                faction: Some(Faction::British),
+                ..Default::default()
+            }),
+            ["twin_beach_2p_mkii", "desert_village_2p_mkiii"]
+        );
+        assert_eq!(
+            query(HistoryFilter {
+                opponent: Some("Other".to_string()),
+                ..Default::default()
+            }),
+            ["twin_beach_2p_mkii"]
+        );
+        // The player running the game is never an opponent
+        assert!(query(HistoryFilter {
+            opponent: Some("Tester".to_string()),
+            ..Default::default()
+        })
+        .is_empty());
+        assert_eq!(
+            query(HistoryFilter {
+                from: Some(matches[1].started_at),
+                to: Some(matches[1].started_at),
+                ..Default::default()
+            }),
+            ["desert_village_2p_mkiii"]
+        );
+    }
+
+    #[test]
+    fn test_match_history_paging() {
+        let (history, matches) = filled_history();
+        let page = history
+            .query(&HistoryFilter {
+                page: 1,
+                page_size: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.matches, [matches[0].clone()]);
+    }
+}
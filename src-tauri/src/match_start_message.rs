@@ -0,0 +1,276 @@
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const MESSAGE_TYPE: &str = "MatchStartMessage";
+
+/// The lobby message Relic sends to every player when a match starts,
+/// see `reference/MatchStartMessage` for a sample
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchStartMessage {
+    /// profile id of the player the message was sent by
+    pub sender_profile_id: u64,
+    pub players: Vec<MatchPlayer>,
+    pub faction_selections: Vec<FactionSelection>,
+    /// unix timestamp in seconds
+    pub start_time: u64,
+    pub items: Vec<PlayerItems>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchPlayer {
+    pub profile_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FactionSelection {
+    pub profile_id: u64,
+    /// relic race id of the selected faction
+    pub race_id: u64,
+}
+
+/// The items (battlegroups, skins, ...) a player brought into the match
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayerItems {
+    pub profile_id: u64,
+    pub items: Vec<ItemEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemEntry {
+    /// id of this item instance in the players inventory
+    pub id: u64,
+    pub slot: i64,
+    /// id of the item definition this item is an instance of
+    pub definition_id: u64,
+    pub profile_id: u64,
+    /// `None` when the item has no metadata
+    pub metadata: Option<ItemMetadata>,
+    /// unix timestamp in seconds
+    pub created_at: u64,
+    /// id of the item this item is equipped to, or a small slot number for top level items
+    pub parent_id: i64,
+    pub location: i64,
+}
+
+/// The JSON object embedded as a string in an item entry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemMetadata {
+    /// equipment position
+    pub epos: Option<String>,
+    /// equipment slot
+    pub eslot: Option<String>,
+    /// every other key, so nothing is lost when Relic adds new ones
+    #[serde(flatten)]
+    pub other: BTreeMap<String, serde_json::Value>,
+}
+
+/// Error returned when a message can not be parsed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MatchStartMessageError {
+    /// the message is not valid JSON or does not have the expected layout
+    InvalidJson(String),
+    /// the message is valid, but not a MatchStartMessage
+    UnexpectedMessageType(String),
+    /// an id that is sent as a string is not a number
+    InvalidId(String),
+}
+
+impl fmt::Display for MatchStartMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchStartMessageError::InvalidJson(message) => {
+                write!(f, "invalid match start message: {}", message)
+            }
+            MatchStartMessageError::UnexpectedMessageType(message_type) => {
+                write!(f, "expected {}, got {}", MESSAGE_TYPE, message_type)
+            }
+            MatchStartMessageError::InvalidId(id) => write!(f, "invalid id: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for MatchStartMessageError {}
+
+// The message is made of nested arrays only, these types mirror the layout:
+// [0, "MatchStartMessage", sender, [players, faction selections, start time, items]]
+#[derive(Deserialize)]
+struct RawMessage(IgnoredAny, String, u64, RawPayload);
+
+#[derive(Deserialize)]
+struct RawPayload(
+    Vec<(u64, IgnoredAny)>,
+    Vec<(String, String)>,
+    u64,
+    Vec<(String, Vec<RawItem>)>,
+);
+
+// [id, slot, definition id, profile id, 1, 0, metadata, created at, parent id, -1, location, -1]
+#[derive(Deserialize)]
+struct RawItem(
+    u64,
+    i64,
+    u64,
+    u64,
+    IgnoredAny,
+    IgnoredAny,
+    String,
+    u64,
+    i64,
+    IgnoredAny,
+    i64,
+    IgnoredAny,
+);
+
+/// parses the raw MatchStartMessage as it is sent by the Relic lobby
+pub fn parse_match_start_message(
+    message: String,
+) -> Result<MatchStartMessage, MatchStartMessageError> {
+    let RawMessage(_, message_type, sender_profile_id, payload) = serde_json::from_str(&message)
+        .map_err(|error| MatchStartMessageError::InvalidJson(error.to_string()))?;
+    if message_type != MESSAGE_TYPE {
+        return Err(MatchStartMessageError::UnexpectedMessageType(message_type));
+    }
+    let RawPayload(raw_players, raw_selections, start_time, raw_items) = payload;
+
+    let players = raw_players
+        .into_iter()
+        .map(|(profile_id, _)| MatchPlayer { profile_id })
+        .collect();
+    let faction_selections = raw_selections
+        .into_iter()
+        .map(|(profile_id, race_id)| {
+            Ok(FactionSelection {
+                profile_id: parse_id(&profile_id)?,
+                race_id: parse_id(&race_id)?,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let items = raw_items
+        .into_iter()
+        .map(|(profile_id, items)| {
+            Ok(PlayerItems {
+                profile_id: parse_id(&profile_id)?,
+                items: items
+                    .into_iter()
+                    .map(get_item_entry)
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(MatchStartMessage {
+        sender_profile_id,
+        players,
+        faction_selections,
+        start_time,
+        items,
+    })
+}
+
+fn parse_id(id: &str) -> Result<u64, MatchStartMessageError> {
+    id.parse::<u64>()
+        .map_err(|_| MatchStartMessageError::InvalidId(id.to_string()))
+}
+
+fn get_item_entry(raw: RawItem) -> Result<ItemEntry, MatchStartMessageError> {
+    let RawItem(
+        id,
+        slot,
+        definition_id,
+        profile_id,
+        _,
+        _,
+        metadata,
+        created_at,
+        parent_id,
+        _,
+        location,
+        _,
+    ) = raw;
+    let metadata = if metadata.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_str(&metadata)
+                .map_err(|error| MatchStartMessageError::InvalidJson(error.to_string()))?,
+        )
+    };
+    Ok(ItemEntry {
+        id,
+        slot,
+        definition_id,
+        profile_id,
+        metadata,
+        created_at,
+        parent_id,
+        location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_match_start_message, MatchStartMessageError};
+
+    const REFERENCE: &str = include_str!("../../reference/MatchStartMessage");
+
+    // The reference sample was cut off in the middle of the last item
+    fn completed_reference() -> String {
+        format!("{}221801,-1,19,-1]]]]]]", REFERENCE.trim_end())
+    }
+
+    #[test]
+    fn test_parse_match_start_message_reference() {
+        let message = parse_match_start_message(completed_reference()).unwrap();
+        assert_eq!(message.sender_profile_id, 5536);
+        assert_eq!(message.start_time, 1673606761);
+        assert_eq!(
+            message
+                .players
+                .iter()
+                .map(|player| player.profile_id)
+                .collect::<Vec<_>>(),
+            [32652, 1863, 46763, 37673, 5536, 32282]
+        );
+        assert_eq!(message.faction_selections.len(), 6);
+        assert_eq!(message.faction_selections[1].profile_id, 1863);
+        assert_eq!(message.faction_selections[1].race_id, 198437);
+
+        let items = &message.items[0];
+        assert_eq!(items.profile_id, 32652);
+        assert_eq!(items.items[0].id, 3977807);
+        assert_eq!(items.items[0].definition_id, 451707);
+        assert_eq!(items.items[0].created_at, 1673484494);
+        assert_eq!(items.items[0].metadata, None);
+        let metadata = items.items[3].metadata.as_ref().unwrap();
+        assert_eq!(metadata.epos.as_deref(), Some("0"));
+        assert_eq!(metadata.eslot.as_deref(), Some("0"));
+        assert!(metadata.other.is_empty());
+        assert_eq!(items.items[3].parent_id, 3977807);
+
+        let last = message.items.last().unwrap();
+        assert_eq!(last.profile_id, 1863);
+        assert_eq!(last.items.last().unwrap().id, 221883);
+    }
+
+    #[test]
+    fn test_parse_match_start_message_truncated() {
+        assert!(matches!(
+            parse_match_start_message(REFERENCE.to_string()),
+            Err(MatchStartMessageError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_match_start_message_unexpected_type() {
+        assert_eq!(
+            parse_match_start_message(
+                r#"[0, "PlatformSessionUpdateMessage", 1, [[], [], 0, []]]"#.to_string()
+            ),
+            Err(MatchStartMessageError::UnexpectedMessageType(
+                "PlatformSessionUpdateMessage".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use crate::parse_log_file::LogFileData;
+use log::{info, warn};
+use serde::Serialize;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+pub const DEFAULT_PORT: u16 = 47824;
+
+// Added to the served overlay so OBS reloads it when it was rendered again
+const RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+  var socket = new WebSocket("ws://" + location.host + "/ws")
+  socket.onmessage = function (event) {
+    if (JSON.parse(event.data).type === "overlay-updated") location.reload()
+  }
+  socket.onclose = function () { setTimeout(function () { location.reload() }, 5000) }
+})()
+</script>"#;
+
+/// A message pushed to every WebSocket client
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "kebab-case")]
+pub enum ServerMessage<'a> {
+    /// the parsed log file data changed
+    CurrentGame(&'a LogFileData),
+    /// the overlay page was rendered again
+    OverlayUpdated,
+}
+
+#[derive(Default)]
+struct Content {
+    game: Option<LogFileData>,
+    html: String,
+    clients: Vec<Sender<String>>,
+}
+
+/// The current game and overlay page, shared between the log watcher and the server.
+/// It is kept up to date while the server is stopped, so a started server serves it right away.
+#[derive(Clone, Default)]
+pub struct OverlayContent(Arc<Mutex<Content>>);
+
+impl OverlayContent {
+    pub fn set_game(&self, data: LogFileData) {
+        let mut content = self.0.lock().unwrap();
+        broadcast(&mut content, &ServerMessage::CurrentGame(&data));
+        content.game = Some(data);
+    }
+
+    pub fn set_html(&self, html: String) {
+        let mut content = self.0.lock().unwrap();
+        content.html = html;
+        broadcast(&mut content, &ServerMessage::OverlayUpdated);
+    }
+
+    fn current_game_json(&self) -> String {
+        serde_json::to_string(&self.0.lock().unwrap().game).unwrap_or_default()
+    }
+
+    fn overlay_html(&self) -> String {
+        let html = &self.0.lock().unwrap().html;
+        if html.is_empty() {
+            return format!(
+                "<!doctype html>\n<html><body>{}</body></html>",
+                RELOAD_SCRIPT
+            );
+        }
+        match html.rfind("</body>") {
+            Some(index) => format!("{}{}{}", &html[..index], RELOAD_SCRIPT, &html[index..]),
+            None => format!("{}{}", html, RELOAD_SCRIPT),
+        }
+    }
+}
+
+fn broadcast(content: &mut Content, message: &ServerMessage) {
+    if let Ok(message) = serde_json::to_string(message) {
+        // Clients whose connection was closed are dropped
+        content
+            .clients
+            .retain(|client| client.send(message.clone()).is_ok());
+    }
+}
+
+/// HTTP and WebSocket server on localhost for OBS browser sources and other tools:
+/// - `/` serves the streamer overlay
+/// - `/api/current-game` returns the current [`LogFileData`] as JSON
+/// - `/ws` pushes every [`ServerMessage`]
+///
+/// The server stops when it is dropped.
+pub struct OverlayServer {
+    port: u16,
+    server: Arc<Server>,
+}
+
+impl OverlayServer {
+    pub fn start(port: u16, content: OverlayContent) -> io::Result<Self> {
+        let server = Server::http(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+            .map_err(|error| io::Error::new(io::ErrorKind::AddrInUse, error.to_string()))?;
+        // Port 0 binds any free port
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map_or(port, |address| address.port());
+        let server = Arc::new(server);
+        let thread_server = server.clone();
+        thread::spawn(move || {
+            info!("Overlay server listening on port {}", port);
+            for request in thread_server.incoming_requests() {
+                handle_request(request, port, &content);
+            }
+            info!("Overlay server on port {} stopped", port);
+        });
+        Ok(OverlayServer { port, server })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for OverlayServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+/// The overlay content and the server, the server is `None` while it is disabled
+#[derive(Default)]
+pub struct OverlayServerState {
+    pub content: OverlayContent,
+    pub server: Mutex<Option<OverlayServer>>,
+}
+
+impl OverlayServerState {
+    /// starts or stops the server, a server running on another port is restarted
+    pub fn configure(&self, enabled: bool, port: u16) -> io::Result<()> {
+        let mut server = self.server.lock().unwrap();
+        if enabled && server.as_ref().map(OverlayServer::port) == Some(port) {
+            return Ok(());
+        }
+        // Stop the old server first so the port is free again
+        *server = None;
+        if enabled {
+            *server = Some(OverlayServer::start(port, self.content.clone())?);
+        }
+        Ok(())
+    }
+}
+
+fn handle_request(request: Request, port: u16, content: &OverlayContent) {
+    if request.method() != &Method::Get {
+        request.respond(Response::empty(405)).ok();
+        return;
+    }
+    // Websites resolving their own domain to 127.0.0.1 send their domain as the host
+    let host = header_value(&request, "Host");
+    if !host.is_some_and(|host| is_local_authority(host, port)) {
+        request.respond(Response::empty(403)).ok();
+        return;
+    }
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let response = match path.as_str() {
+        "/" | "/index.html" => Response::from_string(content.overlay_html())
+            .with_header(header("Content-Type", "text/html; charset=utf-8")),
+        "/api/current-game" => Response::from_string(content.current_game_json())
+            .with_header(header("Content-Type", "application/json")),
+        "/ws" => {
+            // Browsers send the origin of the page, only the served overlay may connect
+            let origin = header_value(&request, "Origin");
+            if origin.is_some_and(|origin| !is_local_origin(origin, port)) {
+                request.respond(Response::empty(403)).ok();
+                return;
+            }
+            accept_web_socket(request, content);
+            return;
+        }
+        _ => Response::from_string("Not Found").with_status_code(404),
+    };
+    request.respond(response).ok();
+}
+
+fn accept_web_socket(request: Request, content: &OverlayContent) {
+    let key =
+        header_value(&request, "Sec-WebSocket-Key").map(|key| derive_accept_key(key.as_bytes()));
+    let Some(accept_key) = key else {
+        request
+            .respond(Response::from_string("Expected a WebSocket request").with_status_code(400))
+            .ok();
+        return;
+    };
+    let response = Response::empty(101).with_header(header("Sec-WebSocket-Accept", &accept_key));
+    let stream = request.upgrade("websocket", response);
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    // Register the client and send the current game while the content is locked,
+    // so no update can get lost in between
+    let (sender, receiver) = mpsc::channel::<String>();
+    {
+        let mut locked = content.0.lock().unwrap();
+        if let Some(game) = &locked.game {
+            if let Ok(message) = serde_json::to_string(&ServerMessage::CurrentGame(game)) {
+                sender.send(message).ok();
+            }
+        }
+        locked.clients.push(sender);
+    }
+    thread::spawn(move || {
+        for message in receiver {
+            if let Err(error) = socket.send(Message::Text(message)) {
+                warn!("Overlay WebSocket client disconnected: {}", error);
+                break;
+            }
+        }
+    });
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn header_value<'a>(request: &'a Request, field: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str())
+}
+
+// The server is only reachable as `127.0.0.1:<port>` or `localhost:<port>`
+fn is_local_authority(authority: &str, port: u16) -> bool {
+    let authority = authority.to_ascii_lowercase();
+    ["127.0.0.1", "localhost"]
+        .iter()
+        .any(|host| authority == format!("{}:{}", host, port))
+}
+
+fn is_local_origin(origin: &str, port: u16) -> bool {
+    origin
+        .strip_prefix("http://")
+        .is_some_and(|authority| is_local_authority(authority, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_local_origin, OverlayContent, OverlayServer};
+    use crate::parse_log_file::{GameState, GameType, LogFileData, TeamData, TeamSide};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn log_file_data() -> LogFileData {
+        let team = TeamData {
+            players: Vec::new(),
+            side: TeamSide::Mixed,
+        };
+        LogFileData {
+            match_id: "".to_string(),
+            game_state: GameState::InGame,
+            game_type: GameType::Custom,
+            timestamp: "18:25:10.000".to_string(),
+            started_at: None,
+            ended_at: None,
+            duration: 0,
+            map: "twin_beach_2p_mkii".to_string(),
+            win_condition: "VictoryPoint".to_string(),
+            left: team.clone(),
+            right: team,
+            player_name: "Tester".to_string(),
+            player_steam_id: "".to_string(),
+            language_code: "en".to_string(),
+        }
+    }
+
+    fn get(port: u16, path: &str) -> String {
+        request(port, path, &format!("Host: localhost:{}\r\n", port))
+    }
+
+    fn request(port: u16, path: &str, headers: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\n{}Connection: close\r\n\r\n",
+            path, headers
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_overlay_server() {
+        let content = OverlayContent::default();
+        let server = OverlayServer::start(0, content.clone()).unwrap();
+        let port = server.port();
+        assert_ne!(port, 0);
+
+        let current_game = get(port, "/api/current-game");
+        assert!(current_game.ends_with("null"));
+        assert!(!current_game.contains("Access-Control-Allow-Origin"));
+        content.set_game(log_file_data());
+        assert!(get(port, "/api/current-game").contains("\"map\":\"twin_beach_2p_mkii\""));
+
+        content.set_html("<html><body><p>overlay</p></body></html>".to_string());
+        let page = get(port, "/");
+        assert!(page.contains("<p>overlay</p><script>"));
+        assert!(page.ends_with("</body></html>"));
+
+        assert!(get(port, "/unknown").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_overlay_server_rejects_foreign_hosts() {
+        let server = OverlayServer::start(0, OverlayContent::default()).unwrap();
+        let port = server.port();
+        let host = |host: &str| format!("Host: {}\r\n", host);
+
+        let local = request(port, "/", &host(&format!("127.0.0.1:{}", port)));
+        assert!(local.starts_with("HTTP/1.1 200"));
+        // A rebound domain sends its own name as the host
+        let rebound = request(
+            port,
+            "/api/current-game",
+            &host(&format!("evil.com:{}", port)),
+        );
+        assert!(rebound.starts_with("HTTP/1.1 403"));
+        assert!(request(port, "/", "").starts_with("HTTP/1.1 403"));
+
+        let upgrade = format!(
+            "{}Upgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+             Sec-WebSocket-Version: 13\r\nOrigin: http://evil.com\r\n",
+            host(&format!("localhost:{}", port))
+        );
+        assert!(request(port, "/ws", &upgrade).starts_with("HTTP/1.1 403"));
+    }
+
+    #[test]
+    fn test_is_local_origin() {
+        assert!(is_local_origin("http://localhost:47824", 47824));
+        assert!(is_local_origin("http://127.0.0.1:47824", 47824));
+        assert!(!is_local_origin("http://localhost:8080", 47824));
+        assert!(!is_local_origin("https://evil.com", 47824));
+        assert!(!is_local_origin("null", 47824));
+    }
+
+    #[test]
+    fn test_overlay_server_web_socket() {
+        let content = OverlayContent::default();
+        content.set_game(log_file_data());
+        let server = OverlayServer::start(0, content.clone()).unwrap();
+        let port = server.port();
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://127.0.0.1:{}/ws", port)).unwrap();
+        let mut next_message = || {
+            let message = socket.read().unwrap().into_text().unwrap();
+            serde_json::from_str::<serde_json::Value>(&message).unwrap()
+        };
+        // The current game is sent right after connecting
+        assert_eq!(next_message()["type"], "current-game");
+
+        let mut data = log_file_data();
+        data.game_state = GameState::Menu;
+        content.set_game(data);
+        let message = next_message();
+        assert_eq!(message["type"], "current-game");
+        assert_eq!(message["data"]["game_state"], "Menu");
+
+        content.set_html("<html><body></body></html>".to_string());
+        assert_eq!(next_message()["type"], "overlay-updated");
+    }
+}
@@ -1,52 +1,178 @@
+use chrono::{DateTime, Days, Local, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone};
 use log::info;
 use rev_lines::RawRevLines;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
 use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum GameState {
     Closed,
     Menu,
     Loading,
     InGame,
+    /// the game is gone without logging that it was closed, only detected by the log watcher
+    Crashed,
+    /// the log file stopped long ago without the game logging that it was closed
+    /// and the game process could not be checked, only detected by the log watcher
+    Unknown,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum GameType {
     Classic,
     AI,
     Custom,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum TeamSide {
     Axis,
     Allies,
     Mixed,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// The faction a player picked, serialized as the race name used in the log file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum Faction {
+    Wehrmacht,
+    /// the wehrmacht under the race name some logs still write
+    WestGerman,
+    AfrikaKorps,
+    Americans,
+    British,
+    /// the british forces on the north african maps
+    BritishAfrica,
+    /// a race name this version does not know yet
+    Unknown(String),
+}
+
+impl Faction {
+    pub const ALL: [Faction; 6] = [
+        Faction::Wehrmacht,
+        Faction::WestGerman,
+        Faction::AfrikaKorps,
+        Faction::Americans,
+        Faction::British,
+        Faction::BritishAfrica,
+    ];
+
+    /// the race name as written to the log file
+    pub fn as_str(&self) -> &str {
+        match self {
+            Faction::Wehrmacht => "german",
+            Faction::WestGerman => "west_german",
+            Faction::AfrikaKorps => "afrika_korps",
+            Faction::Americans => "americans",
+            Faction::British => "british",
+            Faction::BritishAfrica => "british_africa",
+            Faction::Unknown(race) => race,
+        }
+    }
+
+    /// the side the faction fights on, `None` for unknown factions
+    pub fn side(&self) -> Option<TeamSide> {
+        match self {
+            Faction::Wehrmacht | Faction::WestGerman | Faction::AfrikaKorps => Some(TeamSide::Axis),
+            Faction::Americans | Faction::British | Faction::BritishAfrica => {
+                Some(TeamSide::Allies)
+            }
+            Faction::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<&str> for Faction {
+    fn from(race: &str) -> Self {
+        match race {
+            "german" => Faction::Wehrmacht,
+            "west_german" => Faction::WestGerman,
+            "afrika_korps" => Faction::AfrikaKorps,
+            "americans" => Faction::Americans,
+            "british" => Faction::British,
+            "british_africa" => Faction::BritishAfrica,
+            _ => Faction::Unknown(race.to_string()),
+        }
+    }
+}
+
+impl From<String> for Faction {
+    fn from(race: String) -> Self {
+        Faction::from(race.as_str())
+    }
+}
+
+impl From<Faction> for String {
+    fn from(faction: Faction) -> Self {
+        faction.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Faction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PlayerData {
     pub ai: bool,
-    pub faction: String,
+    pub faction: Faction,
     pub relic_id: String,
     pub name: String,
     pub position: u8,
+    /// empty until the player was enriched from the Relic API
     pub steam_id: String,
+    /// -1 until the player was enriched, or when the player is not ranked
     pub rank: i64,
+    #[serde(flatten)]
+    pub profile: PlayerProfile,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// The stats of a human player from the Relic API, all `None` until the player was enriched.
+/// The leaderboard stats are taken from the leaderboard of the lobby size and the faction.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PlayerProfile {
+    pub country: Option<String>,
+    pub level: Option<u64>,
+    pub xp: Option<u64>,
+    pub rating: Option<i64>,
+    pub wins: Option<u64>,
+    pub losses: Option<u64>,
+    pub streak: Option<i64>,
+    pub disputes: Option<u64>,
+    pub drops: Option<u64>,
+    pub rank_total: Option<i64>,
+    pub rank_level: Option<i64>,
+    pub region_rank: Option<i64>,
+    pub region_rank_total: Option<i64>,
+    /// unix timestamp in seconds
+    pub last_match_date: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct TeamData {
     pub players: Vec<PlayerData>,
     pub side: TeamSide,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct LogFileData {
+    /// stable id of the most recent game, see [`compute_match_id`], empty when no game was found
+    pub match_id: String,
     pub game_state: GameState,
     pub game_type: GameType,
     pub timestamp: String,
+    /// when the most recent game started loading as an RFC 3339 date time in local time,
+    /// `None` when no game was found or the date of the session is unknown
+    pub started_at: Option<String>,
+    /// when the most recent game was over, `None` as long as it is not
+    pub ended_at: Option<String>,
     pub duration: u64,
     pub map: String,
     pub win_condition: String,
@@ -57,8 +183,345 @@ pub struct LogFileData {
     pub language_code: String,
 }
 
-#[tauri::command]
-pub fn parse_log_file_reverse(path: String) -> LogFileData {
+/// A single match of the session, as found by [`parse_all_matches`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchRecord {
+    /// stable id of the match, see [`compute_match_id`]
+    pub match_id: String,
+    pub game_type: GameType,
+    /// time code of the line where the match started loading
+    pub timestamp: String,
+    /// when the match started loading as an RFC 3339 date time in local time,
+    /// `None` when the date of the session is unknown
+    pub started_at: Option<String>,
+    /// when the match was over, `None` if it did not reach game over
+    pub ended_at: Option<String>,
+    /// duration in seconds, 0 if the match did not reach game over
+    pub duration: u64,
+    pub map: String,
+    pub win_condition: String,
+    pub left: TeamData,
+    pub right: TeamData,
+    /// the loading screen was over and the mission was started
+    pub started: bool,
+    /// the match was played until the end and the game logged "Game Over"
+    pub game_over: bool,
+    /// the steam name of the player running the game
+    pub player_name: String,
+    /// empty if the session did not log the steam profile
+    pub player_steam_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// the log file does not exist at the given path
+    NotFound(String),
+    /// the log file exists but could not be opened, e.g. locked by another process
+    PermissionDenied(String),
+    /// reading the log file failed after it was opened
+    Io(String),
+    /// the file was read but contains no line in the CoH3 warnings.log format
+    NotACoh3Log(String),
+}
+
+impl ParseError {
+    pub(crate) fn from_open_error(path: &str, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => ParseError::NotFound(path.to_string()),
+            io::ErrorKind::PermissionDenied => ParseError::PermissionDenied(path.to_string()),
+            _ => ParseError::Io(format!("{}: {}", path, error)),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotFound(path) => write!(f, "log file not found: {}", path),
+            ParseError::PermissionDenied(path) => {
+                write!(f, "permission denied while opening log file: {}", path)
+            }
+            ParseError::Io(message) => write!(f, "failed to read log file: {}", message),
+            ParseError::NotACoh3Log(path) => {
+                write!(f, "not a Company of Heroes 3 log file: {}", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A line of the warnings.log that was recognised by [`parse_line`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LogEvent {
+    /// the first line of the log, the date and time the game was launched like `2023-05-10 18:20`
+    SessionStarted { started_at: String },
+    /// the game was closed correctly
+    ApplicationClosed,
+    /// the game app switched its state, `Game` when a match was loaded
+    GameStateChange {
+        timestamp: String,
+        new_state: String,
+    },
+    /// the steam name of the player running the game
+    SteamName { timestamp: String, name: String },
+    /// the steam id of the player running the game
+    SteamProfile { timestamp: String, steam_id: String },
+    /// the language the game is set to
+    Language {
+        timestamp: String,
+        language_code: String,
+    },
+    /// the map of a match that starts loading
+    Scenario { timestamp: String, map: String },
+    WinCondition {
+        timestamp: String,
+        win_condition: String,
+    },
+    /// the loading screen is over and the match is running
+    StartingMission { timestamp: String },
+    HumanPlayer {
+        timestamp: String,
+        side: u8,
+        player: PlayerData,
+    },
+    AiPlayer {
+        timestamp: String,
+        side: u8,
+        player: PlayerData,
+    },
+    /// the match is over, the game runs at 8 frames per second
+    GameOver { timestamp: String, frame: u64 },
+}
+
+impl LogEvent {
+    /// the time code of the line, `None` for lines without one
+    pub fn timestamp(&self) -> Option<&str> {
+        match self {
+            LogEvent::SessionStarted { .. } | LogEvent::ApplicationClosed => None,
+            LogEvent::GameStateChange { timestamp, .. }
+            | LogEvent::SteamName { timestamp, .. }
+            | LogEvent::SteamProfile { timestamp, .. }
+            | LogEvent::Language { timestamp, .. }
+            | LogEvent::Scenario { timestamp, .. }
+            | LogEvent::WinCondition { timestamp, .. }
+            | LogEvent::StartingMission { timestamp }
+            | LogEvent::HumanPlayer { timestamp, .. }
+            | LogEvent::AiPlayer { timestamp, .. }
+            | LogEvent::GameOver { timestamp, .. } => Some(timestamp),
+        }
+    }
+}
+
+/// The severity of a log line, written in front of its time code like `(E)`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+    /// a prefix this version does not know, without the parentheses
+    Unknown(String),
+}
+
+impl LogLevel {
+    fn from_prefix(prefix: &str) -> Self {
+        let letter = prefix.trim_start_matches('(').trim_end_matches(')');
+        match letter {
+            "I" => LogLevel::Info,
+            "W" => LogLevel::Warning,
+            "E" => LogLevel::Error,
+            _ => LogLevel::Unknown(letter.to_string()),
+        }
+    }
+}
+
+/// A timestamped line of the log file split into its fields, see [`parse_log_line`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LogLine {
+    pub level: LogLevel,
+    /// time code like `11:44:07.831`
+    pub time: String,
+    /// id of the thread that wrote the line like `000007332`
+    pub thread: String,
+    /// everything after the thread
+    pub body: String,
+}
+
+impl LogLine {
+    /// the part of the game that wrote the line like `GAME` or `MOD`,
+    /// taken from the body when it starts with `<subsystem> -- `
+    pub fn subsystem(&self) -> Option<&str> {
+        let (subsystem, _) = self.body.split_once(" -- ")?;
+        (!subsystem.is_empty() && !subsystem.contains(' ')).then_some(subsystem)
+    }
+}
+
+/// splits a timestamped line into level, time code, thread and body,
+/// returns `None` for lines like the header that have no time code
+pub fn parse_log_line(line: &str) -> Option<LogLine> {
+    let (body, (level, time, thread)) = get_log_line_fields(trim_line_ending(line)).ok()?;
+    Some(LogLine {
+        level: LogLevel::from_prefix(level),
+        time: time.to_string(),
+        thread: thread.to_string(),
+        body: body.to_string(),
+    })
+}
+
+/// Recognises a single line of the warnings.log, returns `None` for lines that are not of interest
+pub fn parse_line(line: &str) -> Option<LogEvent> {
+    // Is the first line of the log with the time the game was launched
+    if let Ok((started_at, _)) =
+        nom::bytes::complete::tag::<&str, &str, ()>("RelicCoH3 started at ")(line)
+    {
+        return Some(LogEvent::SessionStarted {
+            started_at: started_at.trim().to_string(),
+        });
+    }
+
+    // Is the line when the game is being closed correctly
+    if nom::bytes::complete::tag::<&str, &str, ()>("Application closed")(line).is_ok() {
+        return Some(LogEvent::ApplicationClosed);
+    }
+
+    let (tail, time_code) = get_timestamped_line(line).ok()?;
+    let timestamp = time_code.to_string();
+
+    // Is the line where the game app changes its state, e.g. when a game starts
+    if let Ok((new_state, _)) = get_game_state_change(tail) {
+        return Some(LogEvent::GameStateChange {
+            timestamp,
+            new_state: new_state.to_string(),
+        });
+    }
+
+    // Is the line that logs the player steam id
+    if let Ok((steam_id, _)) = get_game_player_steam_id(tail) {
+        return Some(LogEvent::SteamProfile {
+            timestamp,
+            steam_id: steam_id.to_string(),
+        });
+    }
+
+    let (tail, param) = get_param_line(tail).ok()?;
+    if param == "GAME" {
+        if let Ok((tail, sub_param)) = get_game_sub_param(tail) {
+            return match sub_param {
+                "Scenario" => get_map_name(tail).ok().map(|(map, _)| LogEvent::Scenario {
+                    timestamp,
+                    map: map.to_string(),
+                }),
+                "Win Condition Name" => Some(LogEvent::WinCondition {
+                    timestamp,
+                    win_condition: tail.trim().to_string(),
+                }),
+                "Starting mission" => Some(LogEvent::StartingMission { timestamp }),
+                "Human Player" => {
+                    get_player_data(tail, false).map(|(side, player)| LogEvent::HumanPlayer {
+                        timestamp,
+                        side,
+                        player,
+                    })
+                }
+                "AI Player" => {
+                    get_player_data(tail, true).map(|(side, player)| LogEvent::AiPlayer {
+                        timestamp,
+                        side,
+                        player,
+                    })
+                }
+                _ => None,
+            };
+        }
+
+        // Is the line that logs the playing players name
+        if let Ok((steam_name, _)) = get_game_player_name(tail) {
+            return Some(LogEvent::SteamName {
+                timestamp,
+                name: steam_name.to_string(),
+            });
+        }
+
+        // Is the line that logs the games language
+        if let Ok((game_language, _)) = get_game_language(tail) {
+            return Some(LogEvent::Language {
+                timestamp,
+                language_code: game_language.to_string(),
+            });
+        }
+    } else if param == "MOD" {
+        if let Ok((frame, _)) = get_game_over(tail) {
+            return Some(LogEvent::GameOver {
+                timestamp,
+                frame: frame.trim().parse::<u64>().unwrap_or(0),
+            });
+        }
+    }
+    None
+}
+
+/// Iterates over the recognised lines of a log file read forward, see [`parse_line`]
+pub struct LogEvents<R> {
+    reader: R,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead> LogEvents<R> {
+    pub fn new(reader: R) -> Self {
+        LogEvents {
+            reader,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogEvents<R> {
+    type Item = io::Result<LogEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&self.line);
+                    if let Some(event) = parse_line(trim_line_ending(&line)) {
+                        return Some(Ok(event));
+                    }
+                }
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// Parses the log file at the path, see [`parse_reader`]
+pub fn parse_log_file_reverse(path: String) -> Result<LogFileData, ParseError> {
+    let log_file = File::open(&path).map_err(|error| ParseError::from_open_error(&path, error))?;
+    let file_started_at = file_started_at(&log_file);
+    parse_reverse(log_file, &path, file_started_at)
+}
+
+/// Parses a log from any source by reading it in reverse order until the last game is found,
+/// errors name the source as `<reader>` instead of a path
+pub fn parse_reader<R: Read + Seek>(reader: R) -> Result<LogFileData, ParseError> {
+    parse_reverse(reader, READER_SOURCE, None)
+}
+
+/// Parses the contents of a log, see [`parse_reader`]
+pub fn parse_bytes(contents: &[u8]) -> Result<LogFileData, ParseError> {
+    parse_reader(io::Cursor::new(contents))
+}
+
+const READER_SOURCE: &str = "<reader>";
+
+// `source` names the log in errors, the path for log files, `file_started_at` is used
+// when the log has no header with the session start
+pub(crate) fn parse_reverse<R: Read + Seek>(
+    reader: R,
+    source: &str,
+    file_started_at: Option<NaiveDateTime>,
+) -> Result<LogFileData, ParseError> {
     let mut full_game = false;
     let mut game_running = true;
     let mut game_loading = false;
@@ -67,181 +530,111 @@ pub fn parse_log_file_reverse(path: String) -> LogFileData {
     let mut map = "".to_string();
     let mut win_condition = "".to_string();
     let mut timestamp = "".to_string();
+    let mut scenario_timestamp = "".to_string();
+    let mut game_over_timestamp = "".to_string();
     let mut game_duration: u64 = 0;
     let mut left: Vec<PlayerData> = Vec::new();
     let mut right: Vec<PlayerData> = Vec::new();
+    let mut session_started_at = "".to_string();
     let mut player_name = "".to_string();
     let mut player_steam_id = "".to_string();
     let mut language_code = "".to_string();
+    let mut read_any_line = false;
+    let mut found_log_line = false;
+    // The midnights are counted backwards from the end of the log
+    let mut midnights: u32 = 0;
+    let mut scenario_midnights: u32 = 0;
+    let mut game_over_midnights: u32 = 0;
+    let mut later_time: Option<NaiveTime> = None;
 
     // Read log file in reverse order line by line
-    let log_file = File::open(path).unwrap();
-    let rev_lines = RawRevLines::new(log_file);
+    let rev_lines = RawRevLines::new(reader);
 
     for line in rev_lines {
-        let line = line.unwrap();
+        let line = line.map_err(|error| ParseError::Io(format!("{}: {}", source, error)))?;
         let line = String::from_utf8_lossy(&line);
+        read_any_line = true;
 
-        // Is the line when the game is being closed correctly
-        if nom::bytes::complete::tag::<&str, &str, ()>("Application closed")(line.as_ref()).is_ok()
-        {
-            game_running = false;
-            continue;
+        if is_log_line(&line) {
+            found_log_line = true;
         }
 
-        if let Ok((tail, parsed_timestamp)) = get_timestamped_line(line.as_ref()) {
-            // Is the line where a game starts
-            if is_game_start_line(tail) {
-                timestamp = parsed_timestamp.to_string();
-                continue;
+        let event = parse_line(&line);
+        if let Some(time) = event
+            .as_ref()
+            .and_then(LogEvent::timestamp)
+            .and_then(parse_time_code)
+        {
+            if later_time.is_some_and(|later_time| passed_midnight(time, later_time)) {
+                midnights += 1;
             }
+            later_time = Some(time);
+        }
 
-            // Is the line that logs the player steam id
-            if let Ok((steam_id, _)) = get_game_player_steam_id(tail) {
-                player_steam_id = steam_id.to_string();
-                continue;
+        match event {
+            Some(LogEvent::SessionStarted { started_at }) => {
+                session_started_at = started_at;
+                break;
             }
-
-            if let Ok((tail, param)) = get_param_line(tail) {
-                if param == "GAME" {
-                    if let Ok((tail, sub_param)) = get_game_sub_param(tail) {
-                        if sub_param == "Scenario" {
-                            if let Ok((parsed_map, _)) = get_map_name(tail) {
-                                if !full_game {
-                                    map = parsed_map.to_string();
-                                    //println!("Map {}", map);
-                                    full_game = true;
-                                }
-                            }
-                        } else if sub_param == "Win Condition Name" && !full_game {
-                            win_condition = tail.trim().to_string();
-                            game_loading = true;
-                            //println!("Win Condition {}", win_condition);
-                        } else if sub_param == "Starting mission" && !full_game {
-                            game_started = true;
-                        } else if sub_param == "Human Player" && !full_game {
-                            if let Ok((without_space, _)) = get_without_leading_space(tail) {
-                                if let Ok((tail, position_str)) =
-                                    nom::bytes::complete::take_until1::<&str, &str, ()>(" ")(
-                                        without_space,
-                                    )
-                                {
-                                    if let Ok((tail, _)) =
-                                        nom::bytes::complete::tag::<&str, &str, ()>(" ")(tail)
-                                    {
-                                        if let Ok((faction, front)) =
-                                            get_last_separated_by_space(tail)
-                                        {
-                                            if let Ok((side_str, front)) =
-                                                get_last_separated_by_space(front)
-                                            {
-                                                if let Ok((relic_id, user_name)) =
-                                                    get_last_separated_by_space(front)
-                                                {
-                                                    if let Ok(position) = position_str.parse::<u8>()
-                                                    {
-                                                        if let Ok(side) = side_str.parse::<u8>() {
-                                                            let player_data = PlayerData {
-                                                                ai: false,
-                                                                position,
-                                                                faction: faction.to_string(),
-                                                                relic_id: relic_id.to_string(),
-                                                                name: user_name.to_string(),
-                                                                steam_id: "".to_string(),
-                                                                rank: -1,
-                                                            };
-                                                            if side == 0 {
-                                                                left.push(player_data);
-                                                            } else {
-                                                                right.push(player_data);
-                                                            }
-                                                            //println!("{}", position);
-                                                            //println!("{}", faction);
-                                                            //println!("{}", side);
-                                                            //println!("{}", relic_id);
-                                                            //println!("{}", user_name);
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else if sub_param == "AI Player" && !full_game {
-                            if let Ok((without_space, _)) = get_without_leading_space(tail) {
-                                if let Ok((tail, position_str)) =
-                                    nom::bytes::complete::take_until1::<&str, &str, ()>(" ")(
-                                        without_space,
-                                    )
-                                {
-                                    if let Ok((tail, _)) =
-                                        nom::bytes::complete::tag::<&str, &str, ()>(" ")(tail)
-                                    {
-                                        if let Ok((faction, front)) =
-                                            get_last_separated_by_space(tail)
-                                        {
-                                            if let Ok((side_str, front)) =
-                                                get_last_separated_by_space(front)
-                                            {
-                                                if let Ok((_, user_name)) =
-                                                    get_last_separated_by_space(front)
-                                                {
-                                                    if let Ok(position) = position_str.parse::<u8>()
-                                                    {
-                                                        if let Ok(side) = side_str.parse::<u8>() {
-                                                            let player_data = PlayerData {
-                                                                ai: true,
-                                                                position,
-                                                                faction: faction.to_string(),
-                                                                relic_id: "-1".to_string(),
-                                                                name: user_name.to_string(),
-                                                                steam_id: "".to_string(),
-                                                                rank: -1,
-                                                            };
-                                                            if side == 0 {
-                                                                left.push(player_data);
-                                                            } else {
-                                                                right.push(player_data);
-                                                            }
-                                                            //println!("{}", position);
-                                                            //println!("{}", faction);
-                                                            //println!("{}", side);
-                                                            //println!("{}", user_name);
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        // Is the line that logs the playing players name
-                    } else if let Ok((steam_name, _)) = get_game_player_name(tail) {
-                        player_name = steam_name.to_string();
-                        break;
-
-                        // Is the line that logs the games language
-                    } else if let Ok((game_language, _)) = get_game_language(tail) {
-                        language_code = game_language.to_string();
-                    }
-                } else if param == "MOD" {
-                    if let Ok((duration_str, _)) = get_game_over(tail) {
-                        if !full_game {
-                            if let Ok(duration) = duration_str.parse::<u64>() {
-                                game_duration = duration / 8;
-                                //println!("Game Duration {}s", duration/8);
-                            }
-                            game_ended = true;
-                        }
-                    }
+            Some(LogEvent::ApplicationClosed) => game_running = false,
+            Some(LogEvent::GameStateChange {
+                timestamp: parsed_timestamp,
+                new_state,
+            }) if new_state == "Game" => timestamp = parsed_timestamp,
+            // The log is read backwards, so the first value found is the latest one
+            Some(LogEvent::SteamProfile { steam_id, .. }) if player_steam_id.is_empty() => {
+                player_steam_id = steam_id
+            }
+            Some(LogEvent::Scenario {
+                timestamp: parsed_timestamp,
+                map: parsed_map,
+            }) if !full_game => {
+                scenario_timestamp = parsed_timestamp;
+                scenario_midnights = midnights;
+                map = parsed_map;
+                full_game = true;
+            }
+            Some(LogEvent::WinCondition {
+                win_condition: parsed_win_condition,
+                ..
+            }) if !full_game => {
+                win_condition = parsed_win_condition;
+                game_loading = true;
+            }
+            Some(LogEvent::StartingMission { .. }) if !full_game => game_started = true,
+            Some(
+                LogEvent::HumanPlayer { side, player, .. }
+                | LogEvent::AiPlayer { side, player, .. },
+            ) if !full_game => {
+                if side == 0 {
+                    left.push(player);
+                } else {
+                    right.push(player);
                 }
             }
+            // The log is read on to the first line for the session start
+            Some(LogEvent::SteamName { name, .. }) if player_name.is_empty() => player_name = name,
+            Some(LogEvent::Language {
+                language_code: parsed_language_code,
+                ..
+            }) if language_code.is_empty() => language_code = parsed_language_code,
+            Some(LogEvent::GameOver {
+                timestamp: parsed_timestamp,
+                frame,
+            }) if !full_game => {
+                game_over_timestamp = parsed_timestamp;
+                game_over_midnights = midnights;
+                game_duration = frame / 8;
+                game_ended = true;
+            }
+            _ => {}
         }
     }
 
+    if read_any_line && !found_log_line {
+        return Err(ParseError::NotACoh3Log(source.to_string()));
+    }
+
     let game_state = determine_game_state(game_running, game_ended, game_loading, game_started);
     let left_team = get_team_data(left);
     let right_team = get_team_data(right);
@@ -251,9 +644,41 @@ pub fn parse_log_file_reverse(path: String) -> LogFileData {
         left_team.players.len() + right_team.players.len()
     );
 
-    LogFileData {
+    let session_start = parse_session_start(&session_started_at).or(file_started_at);
+    // `later_time` is the time code of the first line of the session by now
+    let date_time = |time_code: &str, midnights_after: u32| {
+        to_date_time(
+            session_start,
+            later_time,
+            midnights - midnights_after,
+            time_code,
+        )
+    };
+
+    Ok(LogFileData {
+        match_id: if map.is_empty() {
+            "".to_string()
+        } else {
+            compute_match_id(
+                &session_started_at,
+                &scenario_timestamp,
+                &map,
+                &left_team,
+                &right_team,
+            )
+        },
         game_state,
         game_type: determine_game_type(&left_team, &right_team),
+        started_at: if map.is_empty() {
+            None
+        } else {
+            date_time(&scenario_timestamp, scenario_midnights)
+        },
+        ended_at: if game_ended {
+            date_time(&game_over_timestamp, game_over_midnights)
+        } else {
+            None
+        },
         timestamp,
         duration: game_duration,
         map,
@@ -263,7 +688,649 @@ pub fn parse_log_file_reverse(path: String) -> LogFileData {
         player_name,
         player_steam_id,
         language_code,
+    })
+}
+
+/// Reads a log file forward and keeps the byte offset of the last read between calls to [`LogTailer::poll`],
+/// so each poll only parses the lines appended since the previous one.
+pub struct LogTailer {
+    path: PathBuf,
+    offset: u64,
+    identity: Option<FileIdentity>,
+    partial_line: Vec<u8>,
+    found_log_line: bool,
+    state: LogFileState,
+}
+
+impl LogTailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        LogTailer {
+            path: path.into(),
+            offset: 0,
+            identity: None,
+            partial_line: Vec::new(),
+            found_log_line: false,
+            state: LogFileState::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// reads the lines appended since the last poll and returns the updated log file data
+    pub fn poll(&mut self) -> Result<LogFileData, ParseError> {
+        let path = self.path.display().to_string();
+        let mut log_file =
+            File::open(&self.path).map_err(|error| ParseError::from_open_error(&path, error))?;
+        let io_error = |error: io::Error| ParseError::Io(format!("{}: {}", path, error));
+
+        let length = log_file.metadata().map_err(io_error)?.len();
+        let file_started_at = file_started_at(&log_file);
+        let identity = FileIdentity::read(&mut log_file).map_err(io_error)?;
+        let replaced = match &self.identity {
+            Some(last_identity) => !last_identity.is_same_file(&identity),
+            None => false,
+        };
+        if replaced || length < self.offset {
+            // The game overwrites the log file on every launch, start over from the beginning
+            info!("Log file was truncated or replaced, reading it from the start");
+            *self = LogTailer::new(self.path.clone());
+        }
+        self.identity = Some(identity);
+
+        let mut appended = Vec::new();
+        log_file
+            .seek(SeekFrom::Start(self.offset))
+            .map_err(io_error)?;
+        log_file.read_to_end(&mut appended).map_err(io_error)?;
+        self.offset += appended.len() as u64;
+
+        // Only complete lines are parsed, the rest is kept until the game finished writing it
+        self.partial_line.extend_from_slice(&appended);
+        if let Some(last_line_end) = self.partial_line.iter().rposition(|byte| *byte == b'\n') {
+            let rest = self.partial_line.split_off(last_line_end + 1);
+            let complete_lines = std::mem::replace(&mut self.partial_line, rest);
+            for line in complete_lines.split(|byte| *byte == b'\n') {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let line = String::from_utf8_lossy(line);
+                if is_log_line(&line) {
+                    self.found_log_line = true;
+                }
+                if let Some(event) = parse_line(&line) {
+                    self.state.apply_event(event);
+                }
+            }
+        }
+
+        // Complete lines were read but none of them looked like a log line
+        if !self.found_log_line && self.offset > self.partial_line.len() as u64 {
+            return Err(ParseError::NotACoh3Log(path));
+        }
+
+        Ok(self.state.to_log_file_data(file_started_at))
+    }
+}
+
+/// Reads the whole log file forward and returns every match of the session in the order they were played
+pub fn parse_all_matches(path: String) -> Result<Vec<MatchRecord>, ParseError> {
+    let log_file = File::open(&path).map_err(|error| ParseError::from_open_error(&path, error))?;
+    let file_started_at = file_started_at(&log_file);
+    parse_matches(BufReader::new(log_file), &path, file_started_at)
+}
+
+/// returns the last `limit` warning and error lines of the running session, oldest first,
+/// only lines of the given subsystem like `GAME` when it is set
+pub fn recent_game_errors(
+    path: String,
+    limit: usize,
+    subsystem: Option<String>,
+) -> Result<Vec<LogLine>, ParseError> {
+    let log_file = File::open(&path).map_err(|error| ParseError::from_open_error(&path, error))?;
+    let mut errors = Vec::new();
+    for line in RawRevLines::new(log_file) {
+        if errors.len() >= limit {
+            break;
+        }
+        let line = line.map_err(|error| ParseError::Io(format!("{}: {}", path, error)))?;
+        let line = String::from_utf8_lossy(&line);
+        // Earlier sessions are overwritten when the game starts, except in archived logs
+        if let Some(LogEvent::SessionStarted { .. }) = parse_line(&line) {
+            break;
+        }
+        let Some(log_line) = parse_log_line(&line) else {
+            continue;
+        };
+        if !matches!(log_line.level, LogLevel::Warning | LogLevel::Error) {
+            continue;
+        }
+        if subsystem.is_some() && log_line.subsystem() != subsystem.as_deref() {
+            continue;
+        }
+        errors.push(log_line);
+    }
+    errors.reverse();
+    Ok(errors)
+}
+
+// see `parse_reverse` for `source` and `file_started_at`
+pub(crate) fn parse_matches<R: BufRead>(
+    mut reader: R,
+    source: &str,
+    file_started_at: Option<NaiveDateTime>,
+) -> Result<Vec<MatchRecord>, ParseError> {
+    let mut matches: Vec<MatchRecord> = Vec::new();
+    let mut current: Option<MatchRecordState> = None;
+    let mut session_started_at = "".to_string();
+    let mut clock = SessionClock::default();
+    let mut player_name = "".to_string();
+    let mut player_steam_id = "".to_string();
+    let mut read_any_line = false;
+    let mut found_log_line = false;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|error| ParseError::Io(format!("{}: {}", source, error)))?;
+        if read == 0 {
+            break;
+        }
+        read_any_line = true;
+        let line = String::from_utf8_lossy(&line);
+        let line = trim_line_ending(&line);
+
+        if is_log_line(line) {
+            found_log_line = true;
+        }
+
+        let event = parse_line(line);
+        if let Some(time_code) = event.as_ref().and_then(LogEvent::timestamp) {
+            clock.advance(time_code);
+        }
+
+        match event {
+            // Every scenario line starts a new match
+            Some(LogEvent::Scenario { timestamp, map }) => {
+                if let Some(finished) = current.take() {
+                    matches.push(finished.into_match_record(&player_name, &player_steam_id));
+                }
+                let session_start = SessionStart {
+                    started_at: session_started_at.clone(),
+                    date_time: parse_session_start(&session_started_at).or(file_started_at),
+                    first_time: clock.first_time,
+                };
+                current = Some(MatchRecordState::new(
+                    map,
+                    timestamp,
+                    session_start,
+                    clock.midnights,
+                ));
+            }
+            Some(LogEvent::SessionStarted { started_at }) => {
+                // The last match of the previous session is over
+                if let Some(finished) = current.take() {
+                    matches.push(finished.into_match_record(&player_name, &player_steam_id));
+                }
+                session_started_at = started_at;
+                clock = SessionClock::default();
+                player_steam_id.clear();
+            }
+            Some(LogEvent::SteamName { name, .. }) => player_name = name,
+            Some(LogEvent::SteamProfile { steam_id, .. }) if player_steam_id.is_empty() => {
+                player_steam_id = steam_id
+            }
+            Some(event) => {
+                if let Some(current) = current.as_mut() {
+                    current.apply_event(event, clock.midnights);
+                }
+            }
+            None => {}
+        }
+    }
+
+    if read_any_line && !found_log_line {
+        return Err(ParseError::NotACoh3Log(source.to_string()));
     }
+    if let Some(finished) = current.take() {
+        matches.push(finished.into_match_record(&player_name, &player_steam_id));
+    }
+
+    info!("Log file parsed: Found {} matches", matches.len());
+
+    Ok(matches)
+}
+
+// The session a match was played in
+struct SessionStart {
+    /// as logged in the header, empty if the log has none
+    started_at: String,
+    /// parsed from the header or the time of the log file
+    date_time: Option<NaiveDateTime>,
+    first_time: Option<NaiveTime>,
+}
+
+struct MatchRecordState {
+    session: SessionStart,
+    scenario_timestamp: String,
+    scenario_midnights: u32,
+    game_over_timestamp: String,
+    game_over_midnights: u32,
+    timestamp: String,
+    duration: u64,
+    map: String,
+    win_condition: String,
+    left: Vec<PlayerData>,
+    right: Vec<PlayerData>,
+    started: bool,
+    game_over: bool,
+}
+
+impl MatchRecordState {
+    fn new(
+        map: String,
+        scenario_timestamp: String,
+        session: SessionStart,
+        scenario_midnights: u32,
+    ) -> Self {
+        MatchRecordState {
+            session,
+            scenario_timestamp,
+            scenario_midnights,
+            game_over_timestamp: "".to_string(),
+            game_over_midnights: 0,
+            timestamp: "".to_string(),
+            duration: 0,
+            map,
+            win_condition: "".to_string(),
+            left: Vec::new(),
+            right: Vec::new(),
+            started: false,
+            game_over: false,
+        }
+    }
+
+    // `midnights` is the number of midnights passed since the first line of the session
+    fn apply_event(&mut self, event: LogEvent, midnights: u32) {
+        match event {
+            LogEvent::GameStateChange {
+                timestamp,
+                new_state,
+            } if new_state == "Game" && self.timestamp.is_empty() => self.timestamp = timestamp,
+            LogEvent::WinCondition { win_condition, .. } => self.win_condition = win_condition,
+            LogEvent::StartingMission { .. } => self.started = true,
+            LogEvent::HumanPlayer { side, player, .. }
+            | LogEvent::AiPlayer { side, player, .. } => {
+                if side == 0 {
+                    self.left.push(player);
+                } else {
+                    self.right.push(player);
+                }
+            }
+            LogEvent::GameOver { timestamp, frame } => {
+                self.game_over_timestamp = timestamp;
+                self.game_over_midnights = midnights;
+                self.duration = frame / 8;
+                self.game_over = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn into_match_record(self, player_name: &str, player_steam_id: &str) -> MatchRecord {
+        let left_team = get_team_data(self.left);
+        let right_team = get_team_data(self.right);
+        let date_time = |time_code: &str, midnights: u32| {
+            to_date_time(
+                self.session.date_time,
+                self.session.first_time,
+                midnights,
+                time_code,
+            )
+        };
+        MatchRecord {
+            match_id: compute_match_id(
+                &self.session.started_at,
+                &self.scenario_timestamp,
+                &self.map,
+                &left_team,
+                &right_team,
+            ),
+            started_at: date_time(&self.scenario_timestamp, self.scenario_midnights),
+            ended_at: if self.game_over {
+                date_time(&self.game_over_timestamp, self.game_over_midnights)
+            } else {
+                None
+            },
+            game_type: determine_game_type(&left_team, &right_team),
+            // Matches that were cancelled while loading never reach the game state
+            timestamp: if self.timestamp.is_empty() {
+                self.scenario_timestamp
+            } else {
+                self.timestamp
+            },
+            duration: self.duration,
+            map: self.map,
+            win_condition: self.win_condition,
+            left: left_team,
+            right: right_team,
+            started: self.started,
+            game_over: self.game_over,
+            player_name: player_name.to_string(),
+            player_steam_id: player_steam_id.to_string(),
+        }
+    }
+}
+
+// The size of the file head that is compared to notice that the game replaced the log file
+const FILE_HEAD_LENGTH: usize = 128;
+
+#[derive(Clone, PartialEq)]
+struct FileIdentity {
+    created: Option<SystemTime>,
+    #[cfg(unix)]
+    inode: u64,
+    head: Vec<u8>,
+}
+
+impl FileIdentity {
+    fn read(file: &mut File) -> io::Result<Self> {
+        let metadata = file.metadata()?;
+        let mut head = Vec::with_capacity(FILE_HEAD_LENGTH);
+        file.seek(SeekFrom::Start(0))?;
+        file.take(FILE_HEAD_LENGTH as u64).read_to_end(&mut head)?;
+        Ok(FileIdentity {
+            created: metadata.created().ok(),
+            #[cfg(unix)]
+            inode: std::os::unix::fs::MetadataExt::ino(&metadata),
+            head,
+        })
+    }
+
+    fn is_same_file(&self, other: &FileIdentity) -> bool {
+        #[cfg(unix)]
+        if self.inode != other.inode {
+            return false;
+        }
+        // The head of the file can only grow while the game is writing the first lines
+        let common_length = self.head.len().min(other.head.len());
+        self.created == other.created && self.head[..common_length] == other.head[..common_length]
+    }
+}
+
+/// Collects the data of the most recent game while the log file is read forward,
+/// giving the same result as reading it in reverse with [`parse_log_file_reverse`]
+#[derive(Clone)]
+struct LogFileState {
+    game_running: bool,
+    game_loading: bool,
+    game_started: bool,
+    game_ended: bool,
+    map: String,
+    win_condition: String,
+    timestamp: String,
+    scenario_timestamp: String,
+    scenario_midnights: u32,
+    game_over_timestamp: String,
+    game_over_midnights: u32,
+    game_duration: u64,
+    left: Vec<PlayerData>,
+    right: Vec<PlayerData>,
+    session_started_at: String,
+    clock: SessionClock,
+    player_name: String,
+    player_steam_id: String,
+    language_code: String,
+}
+
+impl LogFileState {
+    fn new() -> Self {
+        LogFileState {
+            game_running: true,
+            game_loading: false,
+            game_started: false,
+            game_ended: false,
+            map: "".to_string(),
+            win_condition: "".to_string(),
+            timestamp: "".to_string(),
+            scenario_timestamp: "".to_string(),
+            scenario_midnights: 0,
+            game_over_timestamp: "".to_string(),
+            game_over_midnights: 0,
+            game_duration: 0,
+            left: Vec::new(),
+            right: Vec::new(),
+            session_started_at: "".to_string(),
+            clock: SessionClock::default(),
+            player_name: "".to_string(),
+            player_steam_id: "".to_string(),
+            language_code: "".to_string(),
+        }
+    }
+
+    /// updates the state with the next recognised line of the log file
+    fn apply_event(&mut self, event: LogEvent) {
+        if let Some(time_code) = event.timestamp() {
+            self.clock.advance(time_code);
+        }
+        match event {
+            LogEvent::SessionStarted { started_at } => {
+                *self = LogFileState::new();
+                self.session_started_at = started_at;
+            }
+            LogEvent::ApplicationClosed => self.game_running = false,
+            LogEvent::GameStateChange {
+                timestamp,
+                new_state,
+            } if new_state == "Game" && self.timestamp.is_empty() => self.timestamp = timestamp,
+            LogEvent::SteamProfile { steam_id, .. } if self.player_steam_id.is_empty() => {
+                self.player_steam_id = steam_id
+            }
+            // Every scenario line starts a new game
+            LogEvent::Scenario { timestamp, map } => {
+                self.scenario_timestamp = timestamp;
+                self.scenario_midnights = self.clock.midnights;
+                self.game_over_timestamp = "".to_string();
+                self.game_loading = false;
+                self.game_started = false;
+                self.game_ended = false;
+                self.win_condition = "".to_string();
+                self.game_duration = 0;
+                self.left.clear();
+                self.right.clear();
+                self.map = map;
+            }
+            LogEvent::WinCondition { win_condition, .. } => {
+                self.win_condition = win_condition;
+                self.game_loading = true;
+            }
+            LogEvent::StartingMission { .. } => self.game_started = true,
+            LogEvent::HumanPlayer { side, player, .. }
+            | LogEvent::AiPlayer { side, player, .. } => {
+                if side == 0 {
+                    self.left.push(player);
+                } else {
+                    self.right.push(player);
+                }
+            }
+            LogEvent::SteamName { name, .. } => {
+                // Everything before the steam name belongs to an earlier session,
+                // apart from the session start and language that are logged right before it
+                let session_started_at = std::mem::take(&mut self.session_started_at);
+                let clock = std::mem::take(&mut self.clock);
+                let language_code = std::mem::take(&mut self.language_code);
+                *self = LogFileState::new();
+                self.session_started_at = session_started_at;
+                self.clock = clock;
+                self.language_code = language_code;
+                self.player_name = name;
+            }
+            LogEvent::Language { language_code, .. } if self.language_code.is_empty() => {
+                self.language_code = language_code
+            }
+            LogEvent::GameOver { timestamp, frame } => {
+                self.game_over_timestamp = timestamp;
+                self.game_over_midnights = self.clock.midnights;
+                self.game_duration = frame / 8;
+                self.game_ended = true;
+            }
+            _ => {}
+        }
+    }
+
+    // `file_started_at` is used when the log has no header with the session start
+    fn to_log_file_data(&self, file_started_at: Option<NaiveDateTime>) -> LogFileData {
+        let left_team = get_team_data(self.left.clone());
+        let right_team = get_team_data(self.right.clone());
+        let session_start = parse_session_start(&self.session_started_at).or(file_started_at);
+        let date_time = |time_code: &str, midnights: u32| {
+            to_date_time(session_start, self.clock.first_time, midnights, time_code)
+        };
+        LogFileData {
+            match_id: if self.map.is_empty() {
+                "".to_string()
+            } else {
+                compute_match_id(
+                    &self.session_started_at,
+                    &self.scenario_timestamp,
+                    &self.map,
+                    &left_team,
+                    &right_team,
+                )
+            },
+            game_state: determine_game_state(
+                self.game_running,
+                self.game_ended,
+                self.game_loading,
+                self.game_started,
+            ),
+            game_type: determine_game_type(&left_team, &right_team),
+            timestamp: self.timestamp.clone(),
+            started_at: if self.map.is_empty() {
+                None
+            } else {
+                date_time(&self.scenario_timestamp, self.scenario_midnights)
+            },
+            ended_at: if self.game_ended {
+                date_time(&self.game_over_timestamp, self.game_over_midnights)
+            } else {
+                None
+            },
+            duration: self.game_duration,
+            map: self.map.clone(),
+            win_condition: self.win_condition.clone(),
+            left: left_team,
+            right: right_team,
+            player_name: self.player_name.clone(),
+            player_steam_id: self.player_steam_id.clone(),
+            language_code: self.language_code.clone(),
+        }
+    }
+}
+
+// Lines that seem to go back in time by more than this were written after midnight,
+// smaller steps back happen when threads log out of order
+const MIDNIGHT_THRESHOLD_SECONDS: i64 = 12 * 60 * 60;
+
+/// Counts the midnights passed while the lines of a session are read forward,
+/// the time codes of the log have no date and start over at midnight
+#[derive(Clone, Default)]
+struct SessionClock {
+    /// time code of the first line of the session
+    first_time: Option<NaiveTime>,
+    last_time: Option<NaiveTime>,
+    /// midnights passed since the first line
+    midnights: u32,
+}
+
+impl SessionClock {
+    fn advance(&mut self, time_code: &str) {
+        let Some(time) = parse_time_code(time_code) else {
+            return;
+        };
+        if self
+            .last_time
+            .is_some_and(|last_time| passed_midnight(last_time, time))
+        {
+            self.midnights += 1;
+        }
+        self.first_time.get_or_insert(time);
+        self.last_time = Some(time);
+    }
+}
+
+fn parse_time_code(time_code: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time_code, "%H:%M:%S%.f").ok()
+}
+
+// The header of the log looks like `RelicCoH3 started at 2023-05-10 18:20`
+pub(crate) fn parse_session_start(started_at: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(started_at, "%Y-%m-%d %H:%M").ok()
+}
+
+fn passed_midnight(before: NaiveTime, after: NaiveTime) -> bool {
+    (before - after).num_seconds() > MIDNIGHT_THRESHOLD_SECONDS
+}
+
+// The game creates a new log file on every launch, file systems without creation time
+// only have the modification time
+fn file_started_at(file: &File) -> Option<NaiveDateTime> {
+    let metadata = file.metadata().ok()?;
+    let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+    Some(DateTime::<Local>::from(time).naive_local())
+}
+
+/// turns a time code into an RFC 3339 date time in local time, `first_time` is the time code
+/// of the first line of the session and `midnights` the midnights passed since then
+fn to_date_time(
+    session_start: Option<NaiveDateTime>,
+    first_time: Option<NaiveTime>,
+    midnights: u32,
+    time_code: &str,
+) -> Option<String> {
+    let session_start = session_start?;
+    let time = parse_time_code(time_code)?;
+    // The header only has minutes, the first line can already be written after midnight
+    let mut days = u64::from(midnights);
+    if first_time.is_some_and(|first_time| passed_midnight(session_start.time(), first_time)) {
+        days += 1;
+    }
+    let date = session_start.date().checked_add_days(Days::new(days))?;
+    let date_time = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+    Some(date_time.to_rfc3339_opts(SecondsFormat::Millis, false))
+}
+
+/// Hashes the session start, the time code of the scenario line, the map and the sorted relic ids
+/// of all players. The time codes restart with every launch of the game, the session start keeps
+/// the id of matches from different sessions apart, so the same match gets the same id whether it
+/// is read from the live log or imported later.
+pub fn compute_match_id(
+    session_started_at: &str,
+    scenario_timestamp: &str,
+    map: &str,
+    left_team: &TeamData,
+    right_team: &TeamData,
+) -> String {
+    let mut relic_ids: Vec<&str> = left_team
+        .players
+        .iter()
+        .chain(right_team.players.iter())
+        .map(|player| player.relic_id.as_str())
+        .collect();
+    relic_ids.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for part in [session_started_at, scenario_timestamp, map] {
+        hasher.update(part.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.update(relic_ids.join(",").as_bytes());
+    // Half of the hash is plenty to keep the matches of one player apart
+    hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
 }
 
 fn determine_game_state(running: bool, ended: bool, loading: bool, started: bool) -> GameState {
@@ -307,23 +1374,20 @@ fn get_ai_count(team: &TeamData) -> usize {
     count
 }
 
-fn get_team_data(players: Vec<PlayerData>) -> TeamData {
+pub(crate) fn get_team_data(players: Vec<PlayerData>) -> TeamData {
     let mut mixed = false;
     let mut last = TeamSide::Mixed;
     for player in &players {
-        if player.faction == "german" || player.faction == "west_german" {
-            if last == TeamSide::Allies {
-                mixed = true;
-                break;
-            }
-            last = TeamSide::Axis;
-        } else {
-            if last == TeamSide::Axis {
-                mixed = true;
-                break;
-            }
-            last = TeamSide::Allies;
+        // A team with a faction of unknown side can't be assigned to one side
+        let Some(side) = player.faction.side() else {
+            mixed = true;
+            break;
+        };
+        if last != TeamSide::Mixed && last != side {
+            mixed = true;
+            break;
         }
+        last = side;
     }
     if mixed {
         return TeamData {
@@ -341,24 +1405,42 @@ fn get_team_data(players: Vec<PlayerData>) -> TeamData {
 // (I) [11:43:31.404] [000007332]:
 // (E) [11:44:07.831] [000007332]:
 // if searched tags are found
-// take time code -> eg: 11:44:07.831
+// take level, time code and thread -> eg: (E), 11:44:07.831, 000007332
 // and return remaining line
 // if not stop with error as soon as tag cannot be found
-fn get_timestamped_line(line: &str) -> nom::IResult<&str, &str> {
-    let (tail, _) = nom::bytes::complete::take_until1("[")(line)?;
+fn get_log_line_fields(line: &str) -> nom::IResult<&str, (&str, &str, &str)> {
+    let (tail, level) = nom::bytes::complete::take_until1("[")(line)?;
     let (tail, _) = nom::bytes::complete::tag("[")(tail)?;
     let (tail, time_code) = nom::bytes::complete::take_until1("]")(tail)?;
     let (tail, _) = nom::bytes::complete::tag("]")(tail)?;
-    let (tail, _) = nom::bytes::complete::take_until1("]: ")(tail)?;
+    let (tail, thread) = nom::bytes::complete::take_until1("]: ")(tail)?;
     let (tail, _) = nom::bytes::complete::tag("]: ")(tail)?;
+    let thread = thread.trim_start().trim_start_matches('[');
+    Ok((tail, (level.trim(), time_code, thread)))
+}
+
+// take time code -> eg: 11:44:07.831
+// and return remaining line
+fn get_timestamped_line(line: &str) -> nom::IResult<&str, &str> {
+    let (tail, (_, time_code, _)) = get_log_line_fields(line)?;
     Ok((tail, time_code))
 }
 
-fn is_game_start_line(timestamped_tail: &str) -> bool {
-    nom::bytes::complete::tag::<_, _, nom::error::Error<_>>("GameApp::SetState : new (Game)")(
-        timestamped_tail,
-    )
-    .is_ok()
+pub(crate) fn is_log_line(line: &str) -> bool {
+    get_timestamped_line(line).is_ok()
+}
+
+fn trim_line_ending(line: &str) -> &str {
+    line.trim_end_matches(['\r', '\n'])
+}
+
+// look for lines like this:
+// GameApp::SetState : new (Game) old (LoadingGame)
+// and return the new state -> eg: Game
+fn get_game_state_change(timestamped_tail: &str) -> nom::IResult<&str, ()> {
+    let (tail, _) = nom::bytes::complete::tag("GameApp::SetState : new (")(timestamped_tail)?;
+    let (_, new_state) = nom::bytes::complete::take_until1(")")(tail)?;
+    Ok((new_state, ()))
 }
 
 /*fn get_match_started_line(timestamped_tail: &str) -> nom::IResult<&str, ()> {
@@ -421,6 +1503,40 @@ fn get_last_separated_by_space(line: &str) -> nom::IResult<&str, &str> {
     Ok((tail, front))
 }
 
+// look for player blocks like this:
+//  0 PlayerName 1234567 0 german
+//  1 CPU - Standard -1 1 americans
+// take position, name, relic id, side and faction
+// and return the side the player is on with the player data
+fn get_player_data(player_tail: &str, ai: bool) -> Option<(u8, PlayerData)> {
+    let (without_space, _) = get_without_leading_space(player_tail).ok()?;
+    let (tail, position_str) =
+        nom::bytes::complete::take_until1::<&str, &str, ()>(" ")(without_space).ok()?;
+    let (tail, _) = nom::bytes::complete::tag::<&str, &str, ()>(" ")(tail).ok()?;
+    let (faction, front) = get_last_separated_by_space(tail).ok()?;
+    let (side_str, front) = get_last_separated_by_space(front).ok()?;
+    let (relic_id, user_name) = get_last_separated_by_space(front).ok()?;
+    let position = position_str.parse::<u8>().ok()?;
+    let side = side_str.parse::<u8>().ok()?;
+    Some((
+        side,
+        PlayerData {
+            ai,
+            position,
+            faction: Faction::from(faction),
+            relic_id: if ai {
+                "-1".to_string()
+            } else {
+                relic_id.to_string()
+            },
+            name: user_name.to_string(),
+            steam_id: "".to_string(),
+            rank: -1,
+            profile: PlayerProfile::default(),
+        },
+    ))
+}
+
 fn get_till_last_tag<'a>(line: &'a str, tag: &'a str) -> nom::IResult<&'a str, &'a str> {
     let (tail, front) = nom::bytes::complete::take_until(tag)(line)?;
     let (tail, _) = nom::bytes::complete::tag(tag)(tail)?;
@@ -449,17 +1565,556 @@ fn get_without_leading_space(line: &str) -> nom::IResult<&str, ()> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_log_file_reverse;
+    use super::{
+        compute_match_id, determine_game_type, get_team_data, parse_all_matches, parse_bytes,
+        parse_line, parse_log_file_reverse, parse_log_line, parse_matches, parse_reader,
+        recent_game_errors, Faction, GameState, GameType, LogEvent, LogEvents, LogLevel, LogLine,
+        LogTailer, ParseError, PlayerData, PlayerProfile, TeamSide,
+    };
+    use std::fs::{self, OpenOptions};
+    use std::io::{Cursor, Write};
+
+    const LOG_SESSION_START: &str = r"RelicCoH3 started at 2023-05-10 18:20
+(I) [18:20:01.234] [000012345]: GAME -- [Company of Heroes 3] set to language [en]
+(I) [18:20:03.000] [000012345]: GAME -- Current Steam name is [Tester]
+(I) [18:20:04.000] [000012345]: Found profile: /steam/76561198000000001
+";
+
+    const LOG_GAME: &str = r"(I) [18:25:00.000] [000012345]: GAME -- Scenario: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+(I) [18:25:00.100] [000012345]: GAME -- Win Condition Name: VictoryPoint
+(I) [18:25:00.200] [000012345]: GAME -- Human Player: 0 Tester 1234567 0 americans
+(I) [18:25:00.300] [000012345]: GAME -- AI Player: 1 CPU - Standard -1 1 german
+(I) [18:25:10.000] [000012345]: GameApp::SetState : new (Game) old (LoadingGame)
+(I) [18:25:10.500] [000012345]: GAME -- Starting mission: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+";
+
+    const LOG_GAME_OVER: &str = r"(I) [18:45:00.000] [000012345]: MOD -- Game Over at frame 9600
+";
+
+    fn temp_log_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("coh3-stats-{}-{}.log", name, std::process::id()))
+            .display()
+            .to_string()
+    }
+
+    fn append(path: &str, content: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let log = [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER].concat();
+        let data = parse_bytes(log.as_bytes()).unwrap();
+        assert_eq!(data.game_state, GameState::Menu);
+        assert_eq!(data.game_type, GameType::AI);
+        assert_eq!(data.timestamp, "18:25:10.000");
+        assert_eq!(data.duration, 1200);
+        assert_eq!(data.map, "twin_beach_2p_mkii");
+        assert_eq!(data.win_condition, "VictoryPoint");
+        assert_eq!(data.left.players[0].name, "Tester");
+        assert_eq!(data.left.players[0].relic_id, "1234567");
+        assert_eq!(data.left.players[0].faction, Faction::Americans);
+        assert_eq!(data.left.side, TeamSide::Allies);
+        assert_eq!(data.right.players[0].name, "CPU - Standard");
+        assert_eq!(data.right.side, TeamSide::Axis);
+        assert_eq!(data.player_name, "Tester");
+        assert_eq!(data.player_steam_id, "76561198000000001");
+        assert_eq!(data.language_code, "en");
+        // The game does not log who won
+    }
 
     #[test]
-    fn test_parse_log_file_reverse() {
-        println!("{}", file!());
-        parse_log_file_reverse("tests/warnings.log".to_string());
+    fn test_parse_bytes_latest_settings() {
+        let changed = r"(I) [18:22:00.000] [000012345]: GAME -- [Company of Heroes 3] set to language [de]
+(I) [18:22:01.000] [000012345]: Found profile: /steam/76561198000000002
+";
+        let log = [LOG_SESSION_START, changed, LOG_GAME].concat();
+        let data = parse_bytes(log.as_bytes()).unwrap();
+        // The values logged last win, not the ones at the session start
+        assert_eq!(data.language_code, "de");
+        assert_eq!(data.player_steam_id, "76561198000000002");
+    }
+
+    #[test]
+    fn test_parse_bytes_big_log() {
+        // A long session with many games, only the last one is parsed
+        let mut log = LOG_SESSION_START.to_string();
+        let filler = "(I) [18:30:00.000] [000012345]: GAME -- Some other message\n".repeat(100);
+        while log.len() < 2 * 1024 * 1024 {
+            log.push_str(LOG_GAME);
+            log.push_str(&filler);
+            log.push_str(LOG_GAME_OVER);
+        }
+        log.push_str(LOG_GAME);
+        let data = parse_bytes(log.as_bytes()).unwrap();
+        assert_eq!(data.game_state, GameState::InGame);
+        assert_eq!(data.duration, 0);
+        assert_eq!(data.player_name, "Tester");
+    }
+
+    #[test]
+    fn test_parse_reader() {
+        let log = [LOG_SESSION_START, LOG_GAME].concat();
+        let mut reader = Cursor::new(log.into_bytes());
+        // The reader is read from the end no matter where it is positioned
+        reader.set_position(10);
+        let data = parse_reader(reader).unwrap();
+        assert_eq!(data.game_state, GameState::InGame);
+        assert_eq!(data.map, "twin_beach_2p_mkii");
+        assert_eq!(
+            parse_reader(Cursor::new(b"not a log\n")),
+            Err(ParseError::NotACoh3Log("<reader>".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_log_file_reverse_not_found() {
+        let path = "tests/does-not-exist.log".to_string();
+        match parse_log_file_reverse(path.clone()) {
+            Err(ParseError::NotFound(error_path)) => assert_eq!(error_path, path),
+            _ => panic!("expected ParseError::NotFound"),
+        }
+    }
+
+    #[test]
+    fn test_parse_log_file_reverse_not_a_coh3_log() {
+        let path = std::env::temp_dir().join("coh3-stats-not-a-log.txt");
+        fs::write(&path, "just some text\nthat is not a log file\n").unwrap();
+        let result = parse_log_file_reverse(path.display().to_string());
+        fs::remove_file(&path).ok();
+        assert!(matches!(result, Err(ParseError::NotACoh3Log(_))));
+    }
+
+    #[test]
+    fn test_log_tailer_matches_reverse_parser() {
+        let path = temp_log_path("tailer-reverse");
+        fs::write(&path, [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER].concat()).unwrap();
+        let tailed = LogTailer::new(&path).poll().unwrap();
+        let reversed = parse_log_file_reverse(path.clone()).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(
+            serde_json::to_string(&tailed).unwrap(),
+            serde_json::to_string(&reversed).unwrap()
+        );
+        assert_eq!(tailed.map, "twin_beach_2p_mkii");
+        assert_eq!(tailed.duration, 1200);
+        assert_eq!(tailed.player_name, "Tester");
+    }
+
+    #[test]
+    fn test_log_tailer_reads_appended_lines() {
+        let path = temp_log_path("tailer-append");
+        fs::write(&path, LOG_SESSION_START).unwrap();
+        let mut tailer = LogTailer::new(&path);
+        let data = tailer.poll().unwrap();
+        assert_eq!(data.player_name, "Tester");
+        assert_eq!(data.map, "");
+
+        let (first_half, second_half) = LOG_GAME.split_at(40);
+        append(&path, first_half);
+        assert_eq!(tailer.poll().unwrap().map, "");
+        append(&path, second_half);
+        let data = tailer.poll().unwrap();
+        assert_eq!(data.map, "twin_beach_2p_mkii");
+        assert_eq!(data.left.players.len() + data.right.players.len(), 2);
+        assert!(matches!(data.game_state, super::GameState::InGame));
+
+        append(&path, LOG_GAME_OVER);
+        append(&path, "Application closed without errors\n");
+        let data = tailer.poll().unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(data.duration, 1200);
+        assert!(matches!(data.game_state, super::GameState::Closed));
+    }
+
+    #[test]
+    fn test_log_tailer_detects_truncated_file() {
+        let path = temp_log_path("tailer-truncate");
+        fs::write(&path, [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER].concat()).unwrap();
+        let mut tailer = LogTailer::new(&path);
+        assert_eq!(tailer.poll().unwrap().map, "twin_beach_2p_mkii");
+
+        // The game overwrites the log file when it is launched again
+        fs::write(&path, LOG_SESSION_START.replace("18:20", "20:00")).unwrap();
+        let data = tailer.poll().unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(data.map, "");
+        assert_eq!(data.player_name, "Tester");
+        assert!(matches!(data.game_state, super::GameState::Menu));
+    }
+
+    #[test]
+    fn test_parse_all_matches() {
+        let path = temp_log_path("all-matches");
+        let second_game = LOG_GAME
+            .replace("18:25", "18:50")
+            .replace("twin_beach_2p_mkii", "desert_village_2p_mkiii")
+            .replace(
+                "AI Player: 1 CPU - Standard -1 1 german",
+                "Human Player: 1 Opponent 7654321 1 german",
+            );
+        fs::write(
+            &path,
+            [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER, &second_game].concat(),
+        )
+        .unwrap();
+        let matches = parse_all_matches(path.clone()).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].map, "twin_beach_2p_mkii");
+        assert_eq!(matches[0].timestamp, "18:25:10.000");
+        assert_eq!(matches[0].duration, 1200);
+        assert!(matches[0].started && matches[0].game_over);
+        assert!(matches!(matches[0].game_type, GameType::AI));
+        assert_eq!(matches[0].player_name, "Tester");
+        assert_eq!(matches[0].player_steam_id, "76561198000000001");
+
+        assert_eq!(matches[1].map, "desert_village_2p_mkiii");
+        assert_eq!(matches[1].timestamp, "18:50:10.000");
+        assert_eq!(matches[1].win_condition, "VictoryPoint");
+        assert_eq!(matches[1].right.players[0].relic_id, "7654321");
+        assert!(matches[1].started && !matches[1].game_over);
+        assert!(matches!(matches[1].game_type, GameType::Classic));
+    }
+
+    #[test]
+    fn test_match_id() {
+        let log = [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER].concat();
+        let data = parse_bytes(log.as_bytes()).unwrap();
+        let matches = parse_matches(log.as_bytes(), "test", None).unwrap();
+        assert_eq!(data.match_id.len(), 32);
+        assert_eq!(data.match_id, matches[0].match_id);
+        assert_eq!(
+            data.match_id,
+            compute_match_id(
+                "2023-05-10 18:20",
+                "18:25:00.000",
+                "twin_beach_2p_mkii",
+                &data.right,
+                &data.left
+            )
+        );
+
+        // The same time codes in another session belong to another match
+        let next_session = log.replace("2023-05-10", "2023-05-11");
+        let next_data = parse_bytes(next_session.as_bytes()).unwrap();
+        assert_ne!(next_data.match_id, data.match_id);
+
+        let without_game = parse_bytes(LOG_SESSION_START.as_bytes()).unwrap();
+        assert_eq!(without_game.match_id, "");
+    }
+
+    #[test]
+    fn test_started_at_and_ended_at() {
+        let log = [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER].concat();
+        let data = parse_bytes(log.as_bytes()).unwrap();
+        assert!(data
+            .started_at
+            .unwrap()
+            .starts_with("2023-05-10T18:25:00.000"));
+        assert!(data
+            .ended_at
+            .unwrap()
+            .starts_with("2023-05-10T18:45:00.000"));
+
+        // A game over midnight, the log is read in every way
+        let path = temp_log_path("midnight");
+        let late_log = log
+            .replace("18:2", "23:5")
+            .replace("18:45", "00:15")
+            .replace("at frame 9600", "at frame 14400");
+        fs::write(&path, &late_log).unwrap();
+        let reversed = parse_log_file_reverse(path.clone()).unwrap();
+        let tailed = LogTailer::new(&path).poll().unwrap();
+        let matches = parse_all_matches(path.clone()).unwrap();
+        fs::remove_file(&path).ok();
+        let started_at = reversed.started_at.clone().unwrap();
+        let ended_at = reversed.ended_at.clone().unwrap();
+        assert!(started_at.starts_with("2023-05-10T23:55:00.000"));
+        assert!(ended_at.starts_with("2023-05-11T00:15:00.000"));
+        assert_eq!(tailed.started_at.as_ref(), Some(&started_at));
+        assert_eq!(tailed.ended_at.as_ref(), Some(&ended_at));
+        assert_eq!(matches[0].started_at.as_ref(), Some(&started_at));
+        assert_eq!(matches[0].ended_at.as_ref(), Some(&ended_at));
+
+        // The game was launched right before midnight
+        let (header, lines) = late_log.split_once('\n').unwrap();
+        let after_midnight = format!("{}\n{}", header, lines.replace("23:5", "00:0"));
+        let data = parse_bytes(after_midnight.as_bytes()).unwrap();
+        assert!(data
+            .started_at
+            .unwrap()
+            .starts_with("2023-05-11T00:05:00.000"));
+
+        // Without the header and a file there is no date
+        let without_header = [LOG_SESSION_START.split_once('\n').unwrap().1, LOG_GAME].concat();
+        let data = parse_bytes(without_header.as_bytes()).unwrap();
+        assert_eq!(data.timestamp, "18:25:10.000");
+        assert_eq!(data.started_at, None);
+    }
+
+    #[test]
+    fn test_parse_log_line() {
+        let line =
+            parse_log_line("(E) [11:44:07.831] [000007332]: GAME -- Failed to load file\r\n")
+                .unwrap();
+        assert_eq!(
+            line,
+            LogLine {
+                level: LogLevel::Error,
+                time: "11:44:07.831".to_string(),
+                thread: "000007332".to_string(),
+                body: "GAME -- Failed to load file".to_string(),
+            }
+        );
+        assert_eq!(line.subsystem(), Some("GAME"));
+
+        let line = parse_log_line(
+            "(W) [11:44:08.000] [000007332]: GameApp::SetState : new (Game) old (LoadingGame)",
+        )
+        .unwrap();
+        assert_eq!(line.level, LogLevel::Warning);
+        assert_eq!(line.subsystem(), None);
+        assert_eq!(
+            parse_log_line("(X) [11:44:08.000] [000007332]: MOD -- Test").map(|line| line.level),
+            Some(LogLevel::Unknown("X".to_string()))
+        );
+        assert_eq!(
+            parse_log_line("RelicCoH3 started at 2023-05-10 18:20"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recent_game_errors() {
+        let path = temp_log_path("recent-errors");
+        let previous_session = "(E) [17:00:00.000] [000012345]: GAME -- Previous session\n";
+        let errors = r"(E) [18:21:00.000] [000012345]: SIM -- Desync detected
+(W) [18:21:01.000] [000012345]: GAME -- Missing texture
+(I) [18:21:02.000] [000012345]: GAME -- Not an error
+(E) [18:21:03.000] [000007332]: GAME -- Failed to load file
+";
+        fs::write(
+            &path,
+            [previous_session, LOG_SESSION_START, errors, LOG_GAME].concat(),
+        )
+        .unwrap();
+        let all = recent_game_errors(path.clone(), 10, None).unwrap();
+        let last_two = recent_game_errors(path.clone(), 2, None).unwrap();
+        let game = recent_game_errors(path.clone(), 10, Some("GAME".to_string())).unwrap();
+        fs::remove_file(&path).ok();
+
+        let bodies = |lines: Vec<LogLine>| -> Vec<String> {
+            lines.into_iter().map(|line| line.body).collect()
+        };
+        assert_eq!(
+            bodies(all),
+            [
+                "SIM -- Desync detected",
+                "GAME -- Missing texture",
+                "GAME -- Failed to load file"
+            ]
+        );
+        assert_eq!(
+            bodies(last_two),
+            ["GAME -- Missing texture", "GAME -- Failed to load file"]
+        );
+        assert_eq!(
+            bodies(game),
+            ["GAME -- Missing texture", "GAME -- Failed to load file"]
+        );
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("Application closed without errors"),
+            Some(LogEvent::ApplicationClosed)
+        );
+        assert_eq!(
+            parse_line("(I) [18:20:04.000] [000012345]: GAME -- Current Steam name is [Tester]"),
+            Some(LogEvent::SteamName {
+                timestamp: "18:20:04.000".to_string(),
+                name: "Tester".to_string()
+            })
+        );
+        assert_eq!(
+            parse_line("(I) [18:20:03.000] [000012345]: Found profile: /steam/76561198000000001"),
+            Some(LogEvent::SteamProfile {
+                timestamp: "18:20:03.000".to_string(),
+                steam_id: "76561198000000001".to_string()
+            })
+        );
+        assert_eq!(
+            parse_line(
+                "(I) [18:25:10.000] [000012345]: GameApp::SetState : new (Game) old (LoadingGame)"
+            ),
+            Some(LogEvent::GameStateChange {
+                timestamp: "18:25:10.000".to_string(),
+                new_state: "Game".to_string()
+            })
+        );
+        assert_eq!(
+            parse_line("(I) [18:45:00.000] [000012345]: MOD -- Game Over at frame 9600"),
+            Some(LogEvent::GameOver {
+                timestamp: "18:45:00.000".to_string(),
+                frame: 9600
+            })
+        );
+        match parse_line(
+            "(I) [18:25:00.300] [000012345]: GAME -- AI Player: 1 CPU - Standard -1 1 german",
+        ) {
+            Some(LogEvent::AiPlayer { side, player, .. }) => {
+                assert_eq!(side, 1);
+                assert_eq!(player.name, "CPU - Standard");
+                assert_eq!(player.position, 1);
+                assert!(player.ai);
+            }
+            event => panic!("expected LogEvent::AiPlayer, got {:?}", event),
+        }
+        assert_eq!(
+            parse_line("(I) [18:20:05.000] [000012345]: GAME -- Some other message"),
+            None
+        );
+        assert_eq!(
+            parse_line("RelicCoH3 started at 2023-05-10 18:20"),
+            Some(LogEvent::SessionStarted {
+                started_at: "2023-05-10 18:20".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_log_events() {
+        let log = [LOG_SESSION_START, LOG_GAME, LOG_GAME_OVER].concat();
+        let events: Vec<LogEvent> = LogEvents::new(log.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 11);
+        assert!(matches!(events[0], LogEvent::SessionStarted { .. }));
+        assert_eq!(events[0].timestamp(), None);
+        assert!(matches!(events[1], LogEvent::Language { .. }));
+        assert!(
+            matches!(events[4], LogEvent::Scenario { ref map, .. } if map == "twin_beach_2p_mkii")
+        );
+        assert!(matches!(events[10], LogEvent::GameOver { frame: 9600, .. }));
+        assert_eq!(events[10].timestamp(), Some("18:45:00.000"));
+    }
+
+    fn player(faction: Faction, ai: bool) -> PlayerData {
+        PlayerData {
+            ai,
+            faction,
+            relic_id: "1".to_string(),
+            name: "Player".to_string(),
+            position: 0,
+            steam_id: "".to_string(),
+            rank: -1,
+            profile: PlayerProfile::default(),
+        }
+    }
+
+    #[test]
+    fn test_faction_serialization() {
+        for faction in Faction::ALL {
+            let serialized = serde_json::to_string(&faction).unwrap();
+            assert_eq!(serialized, format!("\"{}\"", faction.as_str()));
+            assert_eq!(
+                serde_json::from_str::<Faction>(&serialized).unwrap(),
+                faction
+            );
+        }
+        let unknown = Faction::from("new_faction");
+        assert_eq!(unknown, Faction::Unknown("new_faction".to_string()));
+        assert_eq!(serde_json::to_string(&unknown).unwrap(), "\"new_faction\"");
+    }
+
+    #[test]
+    fn test_faction_side() {
+        assert_eq!(Faction::Wehrmacht.side(), Some(TeamSide::Axis));
+        assert_eq!(Faction::WestGerman.side(), Some(TeamSide::Axis));
+        assert_eq!(Faction::AfrikaKorps.side(), Some(TeamSide::Axis));
+        assert_eq!(Faction::Americans.side(), Some(TeamSide::Allies));
+        assert_eq!(Faction::British.side(), Some(TeamSide::Allies));
+        assert_eq!(Faction::BritishAfrica.side(), Some(TeamSide::Allies));
+        assert_eq!(Faction::from("west_german").side(), Some(TeamSide::Axis));
+        assert_eq!(Faction::from("west_german").as_str(), "west_german");
+    }
+
+    #[test]
+    fn test_team_side_for_every_faction_pair() {
+        for first in Faction::ALL {
+            for second in Faction::ALL {
+                let team = get_team_data(vec![
+                    player(first.clone(), false),
+                    player(second.clone(), false),
+                ]);
+                let expected = if first.side() == second.side() {
+                    first.side().unwrap()
+                } else {
+                    TeamSide::Mixed
+                };
+                assert_eq!(team.side, expected, "{} and {}", first, second);
+            }
+        }
+        let unknown = get_team_data(vec![player(Faction::from("new_faction"), false)]);
+        assert_eq!(unknown.side, TeamSide::Mixed);
+    }
+
+    #[test]
+    fn test_game_type_for_every_faction_pair() {
+        for left in Faction::ALL {
+            for right in Faction::ALL {
+                let left_team = get_team_data(vec![player(left.clone(), false)]);
+                let right_team = get_team_data(vec![player(right.clone(), false)]);
+                let right_ai_team = get_team_data(vec![player(right.clone(), true)]);
+                if left.side() != right.side() {
+                    assert_eq!(
+                        determine_game_type(&left_team, &right_team),
+                        GameType::Classic,
+                        "{} vs {}",
+                        left,
+                        right
+                    );
+                    assert_eq!(
+                        determine_game_type(&left_team, &right_ai_team),
+                        GameType::AI,
+                        "{} vs AI {}",
+                        left,
+                        right
+                    );
+                } else {
+                    assert_eq!(
+                        determine_game_type(&left_team, &right_team),
+                        GameType::Custom,
+                        "{} vs {}",
+                        left,
+                        right
+                    );
+                }
+            }
+        }
     }
 
     #[test]
-    fn test_parse_log_file_reverse_big_file() {
-        println!("{}", file!());
-        parse_log_file_reverse("tests/warnings-2mb.log".to_string());
+    fn test_game_type_with_afrika_korps() {
+        // Afrika Korps used to be counted as allies which made these lobbies custom games
+        let axis = get_team_data(vec![
+            player(Faction::AfrikaKorps, false),
+            player(Faction::Wehrmacht, false),
+        ]);
+        let allies = get_team_data(vec![
+            player(Faction::British, false),
+            player(Faction::Americans, false),
+        ]);
+        assert_eq!(axis.side, TeamSide::Axis);
+        assert_eq!(determine_game_type(&axis, &allies), GameType::Classic);
     }
 }
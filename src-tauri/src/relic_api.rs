@@ -0,0 +1,635 @@
+use crate::leaderboards::GameMode;
+use crate::parse_log_file::{LogFileData, PlayerData};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const BASE_RELIC_API_URL: &str = "https://coh3-api.reliclink.com";
+
+/// How long the stats of a player are reused before they are requested again
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(120);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A failed request is answered with its error this long instead of being sent again
+const ERROR_CACHE_TTL: Duration = Duration::from_secs(15);
+
+/// The response of `/community/leaderboard/getpersonalstat`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalStatResponse {
+    pub result: ApiResult,
+    #[serde(default)]
+    pub stat_groups: Vec<StatGroup>,
+    #[serde(default)]
+    pub leaderboard_stats: Vec<LeaderboardStat>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ApiResult {
+    pub code: i64,
+    /// "SUCCESS" when the request succeeded
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatGroup {
+    pub id: u64,
+    #[serde(default)]
+    pub name: String,
+    /// 1 for the personal stat group of a single player
+    #[serde(rename = "type")]
+    pub group_type: i64,
+    pub members: Vec<Member>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Member {
+    pub profile_id: u64,
+    /// the platform profile like `/steam/76561198000000001`
+    pub name: String,
+    pub alias: String,
+    pub personal_statgroup_id: u64,
+    #[serde(default)]
+    pub xp: u64,
+    #[serde(default)]
+    pub level: u64,
+    #[serde(default)]
+    pub leaderboardregion_id: i64,
+    #[serde(default)]
+    pub country: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LeaderboardStat {
+    pub statgroup_id: u64,
+    pub leaderboard_id: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub streak: i64,
+    pub disputes: u64,
+    pub drops: u64,
+    /// -1 when the player is not ranked on this leaderboard
+    pub rank: i64,
+    pub ranktotal: i64,
+    pub ranklevel: i64,
+    pub rating: i64,
+    pub regionrank: i64,
+    pub regionranktotal: i64,
+    /// unix timestamp in seconds
+    pub lastmatchdate: i64,
+    #[serde(default)]
+    pub highestrank: i64,
+    #[serde(default)]
+    pub highestranklevel: i64,
+    #[serde(default)]
+    pub highestrating: i64,
+}
+
+/// The stats of a single player taken from a batched response
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PlayerStats {
+    pub member: Option<Member>,
+    /// the stats of every leaderboard the player played on
+    pub leaderboard_stats: Vec<LeaderboardStat>,
+}
+
+/// A player of the log file with the stats found for them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnrichedPlayer {
+    #[serde(flatten)]
+    pub player: PlayerData,
+    /// `None` for AI players and players the API returned nothing for
+    pub stats: Option<PlayerStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnrichedTeams {
+    pub left: Vec<EnrichedPlayer>,
+    pub right: Vec<EnrichedPlayer>,
+}
+
+/// Error returned by the Relic API client
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RelicApiError {
+    /// the request failed or the server answered with an error status
+    Http(String),
+    /// the API answered, but with another result than "SUCCESS"
+    Api(String),
+    /// the response could not be parsed
+    InvalidResponse(String),
+}
+
+impl fmt::Display for RelicApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelicApiError::Http(message) => {
+                write!(f, "request to the Relic API failed: {}", message)
+            }
+            RelicApiError::Api(message) => write!(f, "the Relic API returned: {}", message),
+            RelicApiError::InvalidResponse(message) => {
+                write!(f, "invalid response from the Relic API: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RelicApiError {}
+
+struct CacheEntry {
+    fetched_at: Instant,
+    /// `None` when the API returned nothing for the profile
+    stats: Option<PlayerStats>,
+}
+
+struct FailedRequest {
+    failed_at: Instant,
+    error: RelicApiError,
+}
+
+/// Client for the Relic community API that requests the stats of all players in one request
+/// and caches them per profile id, failed requests are cached shortly as well
+pub struct RelicApiClient {
+    base_url: String,
+    ttl: Duration,
+    agent: ureq::Agent,
+    cache: Mutex<HashMap<u64, CacheEntry>>,
+    failures: Mutex<HashMap<u64, FailedRequest>>,
+}
+
+impl Default for RelicApiClient {
+    fn default() -> Self {
+        Self::new(BASE_RELIC_API_URL, DEFAULT_CACHE_TTL)
+    }
+}
+
+impl RelicApiClient {
+    pub fn new(base_url: impl Into<String>, ttl: Duration) -> Self {
+        RelicApiClient {
+            base_url: base_url.into(),
+            ttl,
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            cache: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// returns the stats of every given profile, only profiles that are not cached are requested
+    pub fn get_personal_stats(
+        &self,
+        profile_ids: &[u64],
+    ) -> Result<HashMap<u64, PlayerStats>, RelicApiError> {
+        let mut stats = HashMap::new();
+        let mut missing = Vec::new();
+        {
+            let cache = self.cache.lock().unwrap();
+            for profile_id in profile_ids {
+                match cache.get(profile_id) {
+                    Some(entry) if entry.fetched_at.elapsed() < self.ttl => {
+                        if let Some(player_stats) = &entry.stats {
+                            stats.insert(*profile_id, player_stats.clone());
+                        }
+                    }
+                    _ if !missing.contains(profile_id) => missing.push(*profile_id),
+                    _ => {}
+                }
+            }
+        }
+        if missing.is_empty() {
+            return Ok(stats);
+        }
+        if let Some(error) = self.recent_failure(&missing) {
+            return Err(error);
+        }
+
+        let response = match self.request_personal_stats(&missing) {
+            Ok(response) => response,
+            Err(error) => {
+                let failed_at = Instant::now();
+                let mut failures = self.failures.lock().unwrap();
+                for profile_id in &missing {
+                    failures.insert(
+                        *profile_id,
+                        FailedRequest {
+                            failed_at,
+                            error: error.clone(),
+                        },
+                    );
+                }
+                return Err(error);
+            }
+        };
+        let fetched_at = Instant::now();
+        let mut by_player = split_by_player(response);
+        let mut cache = self.cache.lock().unwrap();
+        let mut failures = self.failures.lock().unwrap();
+        // Profiles the API returned nothing for are cached too, so they are not requested again
+        for profile_id in missing {
+            let player_stats = by_player.remove(&profile_id);
+            failures.remove(&profile_id);
+            cache.insert(
+                profile_id,
+                CacheEntry {
+                    fetched_at,
+                    stats: player_stats.clone(),
+                },
+            );
+            if let Some(player_stats) = player_stats {
+                stats.insert(profile_id, player_stats);
+            }
+        }
+        Ok(stats)
+    }
+
+    /// returns the stats of every human player in the log file data,
+    /// they can be filled into later data of the same match with [`fill_players`]
+    pub fn get_match_stats(
+        &self,
+        data: &LogFileData,
+    ) -> Result<HashMap<u64, PlayerStats>, RelicApiError> {
+        self.get_personal_stats(&profile_ids(data))
+    }
+
+    /// fills the steam id, rank and profile of every human player in the log file data,
+    /// the leaderboard stats are taken from the leaderboard of the game mode and faction
+    pub fn enrich_log_file_data(&self, data: &mut LogFileData) -> Result<(), RelicApiError> {
+        let stats = self.get_match_stats(data)?;
+        fill_players(data, &stats);
+        Ok(())
+    }
+
+    /// adds the stats of every human player to the players of the log file data
+    pub fn enrich(&self, data: &LogFileData) -> Result<EnrichedTeams, RelicApiError> {
+        let stats = self.get_match_stats(data)?;
+        let mut data = data.clone();
+        fill_players(&mut data, &stats);
+        let enrich_team = |players: Vec<PlayerData>| {
+            players
+                .into_iter()
+                .map(|player| EnrichedPlayer {
+                    stats: profile_id(&player).and_then(|id| stats.get(&id).cloned()),
+                    player,
+                })
+                .collect()
+        };
+        Ok(EnrichedTeams {
+            left: enrich_team(data.left.players),
+            right: enrich_team(data.right.players),
+        })
+    }
+
+    // the error of the last request when every profile failed within the error cache ttl
+    fn recent_failure(&self, profile_ids: &[u64]) -> Option<RelicApiError> {
+        let failures = self.failures.lock().unwrap();
+        let mut error = None;
+        for profile_id in profile_ids {
+            let failure = failures
+                .get(profile_id)
+                .filter(|failure| failure.failed_at.elapsed() < ERROR_CACHE_TTL)?;
+            error = Some(failure.error.clone());
+        }
+        error
+    }
+
+    fn request_personal_stats(
+        &self,
+        profile_ids: &[u64],
+    ) -> Result<PersonalStatResponse, RelicApiError> {
+        let ids: Vec<String> = profile_ids.iter().map(u64::to_string).collect();
+        info!("Requesting personal stats of {} players", ids.len());
+        let body = self
+            .agent
+            .get(&format!(
+                "{}/community/leaderboard/getpersonalstat",
+                self.base_url
+            ))
+            .query("profile_ids", &format!("[{}]", ids.join(",")))
+            .query("title", "coh3")
+            .call()
+            .map_err(|error| RelicApiError::Http(error.to_string()))?
+            .into_string()
+            .map_err(|error| RelicApiError::Http(error.to_string()))?;
+        let response: PersonalStatResponse = serde_json::from_str(&body)
+            .map_err(|error| RelicApiError::InvalidResponse(error.to_string()))?;
+        if response.result.message != "SUCCESS" {
+            return Err(RelicApiError::Api(response.result.message));
+        }
+        Ok(response)
+    }
+}
+
+// AI players have no profile, their relic id is -1
+fn profile_id(player: &PlayerData) -> Option<u64> {
+    if player.ai {
+        return None;
+    }
+    player.relic_id.parse().ok()
+}
+
+fn profile_ids(data: &LogFileData) -> Vec<u64> {
+    data.left
+        .players
+        .iter()
+        .chain(data.right.players.iter())
+        .filter_map(profile_id)
+        .collect()
+}
+
+/// fills the steam id, rank and profile of every human player found in the stats
+pub fn fill_players(data: &mut LogFileData, stats: &HashMap<u64, PlayerStats>) {
+    let mode = GameMode::from_teams(&data.left, &data.right);
+    for team in [&mut data.left, &mut data.right] {
+        for player in team.players.iter_mut() {
+            if let Some(player_stats) = profile_id(player).and_then(|id| stats.get(&id)) {
+                let leaderboard_id = mode.player_leaderboard_id(player);
+                fill_player(player, player_stats, leaderboard_id);
+            }
+        }
+    }
+}
+
+fn fill_player(player: &mut PlayerData, stats: &PlayerStats, leaderboard_id: Option<u64>) {
+    if let Some(member) = &stats.member {
+        // The member name is the platform profile like `/steam/76561198000000001`
+        player.steam_id = member.name.rsplit('/').next().unwrap_or("").to_string();
+        player.profile.country = Some(member.country.clone());
+        player.profile.level = Some(member.level);
+        player.profile.xp = Some(member.xp);
+    }
+    let leaderboard_stat = leaderboard_id.and_then(|id| {
+        stats
+            .leaderboard_stats
+            .iter()
+            .find(|stat| stat.leaderboard_id == id)
+    });
+    if let Some(stat) = leaderboard_stat {
+        player.rank = stat.rank;
+        player.profile.rating = Some(stat.rating);
+        player.profile.wins = Some(stat.wins);
+        player.profile.losses = Some(stat.losses);
+        player.profile.streak = Some(stat.streak);
+        player.profile.disputes = Some(stat.disputes);
+        player.profile.drops = Some(stat.drops);
+        player.profile.rank_total = Some(stat.ranktotal);
+        player.profile.rank_level = Some(stat.ranklevel);
+        player.profile.region_rank = Some(stat.regionrank);
+        player.profile.region_rank_total = Some(stat.regionranktotal);
+        player.profile.last_match_date = Some(stat.lastmatchdate);
+    }
+}
+
+// The leaderboard stats of a player belong to the personal stat group of the member
+fn split_by_player(response: PersonalStatResponse) -> HashMap<u64, PlayerStats> {
+    let mut stats = HashMap::new();
+    for member in response
+        .stat_groups
+        .into_iter()
+        .flat_map(|group| group.members)
+    {
+        let leaderboard_stats = response
+            .leaderboard_stats
+            .iter()
+            .filter(|stat| stat.statgroup_id == member.personal_statgroup_id)
+            .cloned()
+            .collect();
+        stats.insert(
+            member.profile_id,
+            PlayerStats {
+                member: Some(member),
+                leaderboard_stats,
+            },
+        );
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_players, RelicApiClient, RelicApiError};
+    use crate::parse_log_file::{
+        get_team_data, Faction, GameState, GameType, LogFileData, PlayerData, PlayerProfile,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use tiny_http::{Response, Server};
+
+    const RESPONSE: &str = r#"{
+        "result": {"code": 0, "message": "SUCCESS"},
+        "statGroups": [
+            {"id": 11, "name": "", "type": 1, "members": [
+                {"profile_id": 1234567, "name": "/steam/76561198000000001", "alias": "Tester",
+                 "personal_statgroup_id": 11, "xp": 5000, "level": 12, "leaderboardregion_id": 2,
+                 "country": "de"}
+            ]},
+            {"id": 22, "name": "", "type": 1, "members": [
+                {"profile_id": 7654321, "name": "/steam/76561198000000002", "alias": "Enemy",
+                 "personal_statgroup_id": 22, "xp": 100, "level": 1, "leaderboardregion_id": 3,
+                 "country": "us"}
+            ]}
+        ],
+        "leaderboardStats": [
+            {"statgroup_id": 11, "leaderboard_id": 2130257, "wins": 10, "losses": 5, "streak": 2,
+             "disputes": 0, "drops": 1, "rank": 42, "ranktotal": 1000, "ranklevel": 12,
+             "rating": 1300, "regionrank": 7, "regionranktotal": 300, "lastmatchdate": 1683740000,
+             "highestrank": 30, "highestranklevel": 13, "highestrating": 1350},
+            {"statgroup_id": 22, "leaderboard_id": 2130255, "wins": 1, "losses": 1, "streak": -1,
+             "disputes": 0, "drops": 0, "rank": -1, "ranktotal": 1000, "ranklevel": -1,
+             "rating": 1000, "regionrank": -1, "regionranktotal": 300, "lastmatchdate": 1683700000}
+        ]
+    }"#;
+
+    /// answers every request with the body and records the requested urls
+    fn mock_server(body: &'static str) -> (String, Arc<Mutex<Vec<String>>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                recorded.lock().unwrap().push(request.url().to_string());
+                request.respond(Response::from_string(body)).ok();
+            }
+        });
+        (format!("http://127.0.0.1:{}", port), requests)
+    }
+
+    fn player(name: &str, relic_id: &str, faction: Faction, ai: bool) -> PlayerData {
+        PlayerData {
+            ai,
+            faction,
+            relic_id: relic_id.to_string(),
+            name: name.to_string(),
+            position: 0,
+            steam_id: "".to_string(),
+            rank: -1,
+            profile: PlayerProfile::default(),
+        }
+    }
+
+    fn log_file_data() -> LogFileData {
+        LogFileData {
+            match_id: "".to_string(),
+            game_state: GameState::Loading,
+            game_type: GameType::Custom,
+            timestamp: "18:25:10.000".to_string(),
+            started_at: None,
+            ended_at: None,
+            duration: 0,
+            map: "twin_beach_2p_mkii".to_string(),
+            win_condition: "VictoryPoint".to_string(),
+            left: get_team_data(vec![
+                player("Tester", "1234567", Faction::Americans, false),
+                player("CPU - Standard", "-1", Faction::Americans, true),
+            ]),
+            right: get_team_data(vec![player("Enemy", "7654321", Faction::Wehrmacht, false)]),
+            player_name: "Tester".to_string(),
+            player_steam_id: "".to_string(),
+            language_code: "en".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_enrich_batches_players() {
+        let (url, requests) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::from_secs(60));
+        let teams = client.enrich(&log_file_data()).unwrap();
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["/community/leaderboard/getpersonalstat?profile_ids=%5B1234567%2C7654321%5D&title=coh3"]
+        );
+        let tester = teams.left[0].stats.as_ref().unwrap();
+        assert_eq!(tester.member.as_ref().unwrap().country, "de");
+        assert_eq!(tester.leaderboard_stats.len(), 1);
+        assert_eq!(tester.leaderboard_stats[0].rating, 1300);
+        assert_eq!(teams.left[1].stats, None);
+        let enemy = teams.right[0].stats.as_ref().unwrap();
+        assert_eq!(enemy.leaderboard_stats[0].leaderboard_id, 2130255);
+        assert_eq!(enemy.leaderboard_stats[0].highestrating, 0);
+    }
+
+    #[test]
+    fn test_enrich_log_file_data() {
+        let (url, _) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::from_secs(60));
+        let mut data = log_file_data();
+        data.left = get_team_data(vec![data.left.players[0].clone()]);
+        client.enrich_log_file_data(&mut data).unwrap();
+
+        let enemy = &data.right.players[0];
+        assert_eq!(enemy.steam_id, "76561198000000002");
+        assert_eq!(enemy.rank, -1);
+        assert_eq!(
+            enemy.profile,
+            PlayerProfile {
+                country: Some("us".to_string()),
+                level: Some(1),
+                xp: Some(100),
+                rating: Some(1000),
+                wins: Some(1),
+                losses: Some(1),
+                streak: Some(-1),
+                disputes: Some(0),
+                drops: Some(0),
+                rank_total: Some(1000),
+                rank_level: Some(-1),
+                region_rank: Some(-1),
+                region_rank_total: Some(300),
+                last_match_date: Some(1_683_700_000),
+            }
+        );
+        let tester = &data.left.players[0];
+        assert_eq!(tester.steam_id, "76561198000000001");
+        assert_eq!(tester.rank, 42);
+        assert_eq!(tester.profile.rating, Some(1300));
+        assert_eq!(tester.profile.drops, Some(1));
+        assert_eq!(tester.profile.rank_level, Some(12));
+        assert_eq!(tester.profile.region_rank_total, Some(300));
+    }
+
+    #[test]
+    fn test_enrich_log_file_data_against_ai() {
+        let (url, _) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::from_secs(60));
+        let mut data = log_file_data();
+        client.enrich_log_file_data(&mut data).unwrap();
+
+        // Games against AI are not ranked, only the profile is filled
+        let tester = &data.left.players[0];
+        assert_eq!(tester.steam_id, "76561198000000001");
+        assert_eq!(tester.rank, -1);
+        assert_eq!(tester.profile.country.as_deref(), Some("de"));
+        assert_eq!(tester.profile.rating, None);
+        assert_eq!(data.left.players[1], log_file_data().left.players[1]);
+    }
+
+    #[test]
+    fn test_fill_players_from_match_stats() {
+        let (url, requests) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::ZERO);
+        let mut data = log_file_data();
+        let stats = client.get_match_stats(&data).unwrap();
+        // Later data of the same match is filled without another request
+        data.game_state = GameState::InGame;
+        fill_players(&mut data, &stats);
+
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(data.left.players[0].steam_id, "76561198000000001");
+        assert_eq!(data.right.players[0].profile.country.as_deref(), Some("us"));
+    }
+
+    #[test]
+    fn test_personal_stats_are_cached() {
+        let (url, requests) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::from_secs(60));
+        client.get_personal_stats(&[1234567]).unwrap();
+        // Only the profile that is not cached yet is requested
+        let stats = client.get_personal_stats(&[1234567, 7654321]).unwrap();
+        client.get_personal_stats(&[7654321, 1234567]).unwrap();
+
+        assert_eq!(stats.len(), 2);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains("profile_ids=%5B1234567%5D"));
+        assert!(requests[1].contains("profile_ids=%5B7654321%5D"));
+    }
+
+    #[test]
+    fn test_personal_stats_cache_expires() {
+        let (url, requests) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::ZERO);
+        client.get_personal_stats(&[1234567]).unwrap();
+        client.get_personal_stats(&[1234567]).unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_personal_stats_api_error() {
+        let (url, requests) =
+            mock_server(r#"{"result": {"code": 6, "message": "UNREGISTERED_PROFILE_NAME"}}"#);
+        let client = RelicApiClient::new(url, Duration::from_secs(60));
+        let error = Err(RelicApiError::Api("UNREGISTERED_PROFILE_NAME".to_string()));
+        assert_eq!(client.get_personal_stats(&[1]), error);
+        // The failed request is not sent again right away
+        assert_eq!(client.get_personal_stats(&[1]), error);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        client.get_personal_stats(&[1, 2]).ok();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_personal_stats_without_stats_are_cached() {
+        let (url, requests) = mock_server(RESPONSE);
+        let client = RelicApiClient::new(url, Duration::from_secs(60));
+        // The API returns nothing for the first profile
+        let stats = client.get_personal_stats(&[1, 1234567]).unwrap();
+        assert_eq!(stats.len(), 1);
+        let stats = client.get_personal_stats(&[1]).unwrap();
+        assert!(stats.is_empty());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,82 @@
+//! WebAssembly exports of the parser, the log is passed in as its contents since there is no file system
+
+use crate::parse_log_file::{parse_matches, parse_reverse};
+use serde::Serialize;
+use std::io::Cursor;
+use wasm_bindgen::prelude::*;
+
+// Used in errors instead of a path
+const SOURCE: &str = "warnings.log";
+
+/// parses the contents of a warnings.log and returns the `LogFileData` of the last game
+#[wasm_bindgen(js_name = parseLogFile)]
+pub fn parse_log_file(contents: &[u8]) -> Result<JsValue, JsValue> {
+    to_js(parse_reverse(Cursor::new(contents), SOURCE, None))
+}
+
+/// same as [`parse_log_file`] for the contents as text
+#[wasm_bindgen(js_name = parseLogFileText)]
+pub fn parse_log_file_text(contents: &str) -> Result<JsValue, JsValue> {
+    parse_log_file(contents.as_bytes())
+}
+
+/// parses the contents of a warnings.log and returns every match of the session
+#[wasm_bindgen(js_name = parseAllMatches)]
+pub fn parse_all_matches(contents: &[u8]) -> Result<JsValue, JsValue> {
+    to_js(parse_matches(contents, SOURCE, None))
+}
+
+/// same as [`parse_all_matches`] for the contents as text
+#[wasm_bindgen(js_name = parseAllMatchesText)]
+pub fn parse_all_matches_text(contents: &str) -> Result<JsValue, JsValue> {
+    parse_all_matches(contents.as_bytes())
+}
+
+// Errors are returned as the same objects the desktop app gets from the Tauri commands
+fn to_js<T: Serialize, E: Serialize>(result: Result<T, E>) -> Result<JsValue, JsValue> {
+    match result {
+        Ok(value) => serde_wasm_bindgen::to_value(&value).map_err(JsValue::from),
+        Err(error) => Err(serde_wasm_bindgen::to_value(&error)?),
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::{parse_all_matches_text, parse_log_file, parse_log_file_text};
+    use crate::parse_log_file::{GameState, LogFileData, MatchRecord, ParseError};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const LOG: &str = r"RelicCoH3 started at 2023-05-10 18:20
+(I) [18:20:04.000] [000012345]: GAME -- Current Steam name is [Tester]
+(I) [18:25:00.000] [000012345]: GAME -- Scenario: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+(I) [18:25:00.200] [000012345]: GAME -- Human Player: 0 Tester 1234567 0 americans
+(I) [18:25:00.300] [000012345]: GAME -- AI Player: 1 CPU - Standard -1 1 german
+(I) [18:25:10.000] [000012345]: GameApp::SetState : new (Game) old (LoadingGame)
+(I) [18:25:10.500] [000012345]: GAME -- Starting mission: data:scenarios\multiplayer\twin_beach_2p_mkii\twin_beach_2p_mkii
+";
+
+    #[wasm_bindgen_test]
+    fn test_parse_log_file() {
+        let data: LogFileData =
+            serde_wasm_bindgen::from_value(parse_log_file_text(LOG).unwrap()).unwrap();
+        assert_eq!(data.game_state, GameState::InGame);
+        assert_eq!(data.map, "twin_beach_2p_mkii");
+        assert_eq!(data.player_name, "Tester");
+        assert_eq!(data.left.players[0].relic_id, "1234567");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_all_matches() {
+        let matches: Vec<MatchRecord> =
+            serde_wasm_bindgen::from_value(parse_all_matches_text(LOG).unwrap()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].started);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_log_file_not_a_coh3_log() {
+        let error: ParseError =
+            serde_wasm_bindgen::from_value(parse_log_file(b"not a log\n").unwrap_err()).unwrap();
+        assert_eq!(error, ParseError::NotACoh3Log("warnings.log".to_string()));
+    }
+}
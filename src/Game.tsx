@@ -1,11 +1,16 @@
-import { useGameData } from "./game-data-provider/GameDataProvider"
-import { Title, Grid, Loader, Group, Box, Badge } from "@mantine/core"
+import {
+  useGameData,
+  useLogFileParseError,
+} from "./game-data-provider/GameDataProvider"
+import { Title, Grid, Loader, Group, Box, Badge, Text } from "@mantine/core"
 import { PlayerCard } from "./components/PlayerCard"
 import { useLogFilePath } from "./game-data-provider/configValues"
 import { OnlinePlayers } from "./components/Online-players"
+import { describeLogFileParseError } from "./game-data-provider/GameData"
 
 export const Game: React.FC = () => {
   const gameData = useGameData()
+  const parseError = useLogFileParseError()
   const logFilePath = useLogFilePath()
   return (
     <>
@@ -49,6 +54,10 @@ export const Game: React.FC = () => {
                 </Grid.Col>
               </Grid>
             </>
+          ) : parseError !== undefined ? (
+            <Group position="center" mt={50}>
+              <Text color="red">{describeLogFileParseError(parseError)}</Text>
+            </Group>
           ) : (
             <Group position="center" mt={50}>
               <Title>
@@ -13,6 +13,7 @@ import {
   Checkbox,
   Slider,
   Anchor,
+  NumberInput,
 } from "@mantine/core"
 import { appDataDir } from "@tauri-apps/api/path"
 import { writeText } from "@tauri-apps/api/clipboard"
@@ -20,7 +21,12 @@ import { useEffect, useState } from "react"
 import { IconCheck, IconCopy, IconPlayerPlay, IconX } from "@tabler/icons-react"
 import { open } from "@tauri-apps/api/dialog"
 import { open as openLink } from "@tauri-apps/api/shell"
-import { useLogFilePath } from "./game-data-provider/configValues"
+import { invoke } from "@tauri-apps/api/tauri"
+import {
+  useLogArchiveMaxFiles,
+  useLogArchiveMaxMegabytes,
+  useLogFilePath,
+} from "./game-data-provider/configValues"
 import {
   usePlaySound,
   usePlaySoundVolume,
@@ -28,19 +34,43 @@ import {
 import {
   useShowFlagsOverlay,
   useAlwaysShowOverlay,
+  useOverlayServerEnabled,
+  useOverlayServerPort,
 } from "./streamer-overlay/configValues"
 import { playSound as playSoundFunc } from "./game-found-sound/playSound"
 import events from "./mixpanel/mixpanel"
-import { useGameData } from "./game-data-provider/GameDataProvider"
+import {
+  useGameData,
+  useLogFileParseError,
+} from "./game-data-provider/GameDataProvider"
+import {
+  LogFileReport,
+  describeLogFileParseError,
+  describeLogFileProblem,
+  isLogFileUnusable,
+} from "./game-data-provider/GameData"
 
 export const Settings: React.FC = () => {
   const gameData = useGameData()
+  const parseError = useLogFileParseError()
   const [logFilePath, setLogFilePath] = useLogFilePath()
+  const [logArchiveMaxFiles, setLogArchiveMaxFiles] = useLogArchiveMaxFiles()
+  const [logArchiveMaxMegabytes, setLogArchiveMaxMegabytes] =
+    useLogArchiveMaxMegabytes()
   const [playSound, setPlaySound] = usePlaySound()
   const [playSoundVolume, setPlaySoundVolume] = usePlaySoundVolume()
   const [showFlagsOverlay, setShowFlagsOverlay] = useShowFlagsOverlay()
   const [alwaysShowOverlay, setAlwaysShowOverlay] = useAlwaysShowOverlay()
+  const [overlayServerEnabled, setOverlayServerEnabled] =
+    useOverlayServerEnabled()
+  const [overlayServerPort, setOverlayServerPort] = useOverlayServerPort()
+  const [overlayServerPortInput, setOverlayServerPortInput] = useState<
+    number | ""
+  >("")
   const [appDataPath, setAppDataPath] = useState<string>("")
+  const [logFileError, setLogFileError] = useState<string>()
+  const [logFileWarning, setLogFileWarning] = useState<string>()
+  const logFileValid = logFilePath !== undefined && logFileError === undefined
 
   useEffect(() => {
     events.open_settings()
@@ -56,6 +86,42 @@ export const Settings: React.FC = () => {
     }
   }, [appDataPath])
 
+  useEffect(() => {
+    if (overlayServerPort !== undefined) {
+      setOverlayServerPortInput(overlayServerPort)
+    }
+  }, [overlayServerPort])
+
+  const configureOverlayServer = async (enabled: boolean, port: number) => {
+    try {
+      await invoke("set_overlay_server", { enabled, port })
+    } catch (e) {
+      console.error(e)
+    }
+  }
+
+  // the port is only applied once it is entered completely
+  const applyOverlayServerPort = () => {
+    const port = overlayServerPortInput
+    if (port === "" || !Number.isInteger(port) || port < 1024 || port > 65535) {
+      setOverlayServerPortInput(overlayServerPort ?? 47824)
+      return
+    }
+    if (port === overlayServerPort) {
+      return
+    }
+    events.settings_changed("overlayServerPort", `${port}`)
+    setOverlayServerPort(port)
+    configureOverlayServer(overlayServerEnabled ?? false, port)
+  }
+
+  // the archive limits are read when the backend starts watching the log file
+  const restartLogWatcher = () => {
+    if (logFilePath !== undefined) {
+      invoke("watch_log_file", { path: logFilePath })
+    }
+  }
+
   const openDialog = async () => {
     const selected = await open({
       title: "Select Coh3 warnings.log file",
@@ -70,6 +136,20 @@ export const Settings: React.FC = () => {
       ],
     })
     if (selected !== null) {
+      const report = (await invoke("validate_log_file", {
+        path: selected as string,
+      })) as LogFileReport
+      if (report.problem !== null && isLogFileUnusable(report.problem)) {
+        setLogFileError(describeLogFileProblem(report.problem))
+        return
+      }
+      setLogFileError(undefined)
+      // An empty or unknown file is used anyway, the game may still write to it
+      setLogFileWarning(
+        report.problem !== null
+          ? describeLogFileProblem(report.problem)
+          : undefined
+      )
       events.settings_changed("logFilePath", selected as string)
       setLogFilePath(selected as string)
     }
@@ -101,17 +181,19 @@ export const Settings: React.FC = () => {
                 </Group>
                 <Tooltip
                   label={
-                    logFilePath !== undefined
+                    logFileError !== undefined
+                      ? logFileError
+                      : logFilePath !== undefined
                       ? "Log file found"
                       : "Could not find log file"
                   }
                 >
                   <ActionIcon
                     variant="light"
-                    color={logFilePath !== undefined ? "green" : "red"}
+                    color={logFileValid ? "green" : "red"}
                     radius="xl"
                   >
-                    {logFilePath !== undefined ? (
+                    {logFileValid ? (
                       <IconCheck size="1.125rem" />
                     ) : (
                       <IconX size="1.125rem" />
@@ -119,8 +201,51 @@ export const Settings: React.FC = () => {
                   </ActionIcon>
                 </Tooltip>
               </Group>
+              {logFileWarning !== undefined ? (
+                <Text color="yellow" size="sm">
+                  {logFileWarning}
+                </Text>
+              ) : null}
+              {parseError !== undefined ? (
+                <Text color="red" size="sm">
+                  {describeLogFileParseError(parseError)}
+                </Text>
+              ) : null}
             </div>
           </Group>
+          <Group>
+            <div>Keep previous logs:</div>
+            <NumberInput
+              value={logArchiveMaxFiles}
+              min={1}
+              max={1000}
+              style={{ width: 80 }}
+              onChange={(value) => {
+                if (value === "") {
+                  return
+                }
+                events.settings_changed("logArchiveMaxFiles", `${value}`)
+                setLogArchiveMaxFiles(value)
+              }}
+              onBlur={restartLogWatcher}
+            />
+            <Text>files, up to</Text>
+            <NumberInput
+              value={logArchiveMaxMegabytes}
+              min={1}
+              max={10000}
+              style={{ width: 80 }}
+              onChange={(value) => {
+                if (value === "") {
+                  return
+                }
+                events.settings_changed("logArchiveMaxMegabytes", `${value}`)
+                setLogArchiveMaxMegabytes(value)
+              }}
+              onBlur={restartLogWatcher}
+            />
+            <Text>MB</Text>
+          </Group>
           <Group>
             <div>Play sound on match found:</div>
             <div>
@@ -205,6 +330,42 @@ export const Settings: React.FC = () => {
               />
             </div>
           </Group>
+          <Group>
+            <div>Serve overlay on http://localhost:</div>
+            <div>
+              <Checkbox
+                checked={
+                  overlayServerEnabled === undefined
+                    ? false
+                    : overlayServerEnabled
+                }
+                onChange={(event) => {
+                  events.settings_changed(
+                    "overlayServerEnabled",
+                    `${event.currentTarget.checked}`
+                  )
+                  setOverlayServerEnabled(event.currentTarget.checked)
+                  configureOverlayServer(
+                    event.currentTarget.checked,
+                    overlayServerPort ?? 47824
+                  )
+                }}
+              />
+            </div>
+            <NumberInput
+              value={overlayServerPortInput}
+              min={1024}
+              max={65535}
+              style={{ width: 100 }}
+              onChange={setOverlayServerPortInput}
+              onBlur={applyOverlayServerPort}
+              onKeyDown={(event) => {
+                if (event.key === "Enter") {
+                  applyOverlayServerPort()
+                }
+              }}
+            />
+          </Group>
           <div>
             <Text weight={700}>
               Follow the{" "}
@@ -1,7 +1,15 @@
 import { MantineColor } from "@mantine/core"
 import { logFileRaceType, raceType } from "coh3-data-types-library"
 
-export type GameState = "Closed" | "Menu" | "Loading" | "InGame"
+export type GameState =
+  | "Closed"
+  | "Menu"
+  | "Loading"
+  | "InGame"
+  /** The game is gone without logging that it was closed */
+  | "Crashed"
+  /** The log file stopped long ago and the game process could not be checked */
+  | "Unknown"
 
 /** Classic means pvp axis vs allies mode like automatch */
 export type GameType = "Classic" | "AI" | "Custom"
@@ -14,8 +22,25 @@ export interface RawPlayerData {
   relic_id: string
   name: string
   position: number
+  /** Empty until the player was enriched from the Relic API */
   steam_id: string
+  /** -1 until the player was enriched, or when the player is not ranked */
   rank: number
+  country: string | null
+  level: number | null
+  xp: number | null
+  rating: number | null
+  wins: number | null
+  losses: number | null
+  streak: number | null
+  disputes: number | null
+  drops: number | null
+  rank_total: number | null
+  rank_level: number | null
+  region_rank: number | null
+  region_rank_total: number | null
+  /** Unix timestamp in seconds */
+  last_match_date: number | null
 }
 
 export interface RawTeamData {
@@ -24,10 +49,16 @@ export interface RawTeamData {
 }
 
 export interface RawGameData {
+  /** Stable id of the last game computed by the backend, empty when no game was found */
+  match_id: string
   game_state: GameState
   game_type: GameType
   /** Timestamp in log file when the last game started. This timestamp represents the time since coh3 was launched! */
   timestamp: string
+  /** RFC 3339 date time in local time when the last game started loading, null when the date of the session is unknown */
+  started_at: string | null
+  /** RFC 3339 date time in local time when the last game was over, null as long as it is not */
+  ended_at: string | null
   /** Duration in seconds */
   duration: number
   map: string
@@ -39,6 +70,87 @@ export interface RawGameData {
   language_code: string
 }
 
+/** Error returned by the backend when the log file could not be parsed */
+export type LogFileParseError =
+  | { NotFound: string }
+  | { PermissionDenied: string }
+  | { Io: string }
+  | { NotACoh3Log: string }
+
+export const describeLogFileParseError = (error: LogFileParseError): string => {
+  if ("NotFound" in error) return "Log file not found: " + error.NotFound
+  if ("PermissionDenied" in error)
+    return "No permission to read the log file: " + error.PermissionDenied
+  if ("NotACoh3Log" in error)
+    return "File is not a CoH3 warnings.log: " + error.NotACoh3Log
+  return "Log file could not be read: " + error.Io
+}
+
+/** Severity of a line in the log file */
+export type LogLevel = "Info" | "Warning" | "Error" | { Unknown: string }
+
+/** A line of the log file returned by the get_recent_game_errors command */
+export interface LogLine {
+  level: LogLevel
+  /** Time code like 11:44:07.831 */
+  time: string
+  /** Id of the thread that wrote the line */
+  thread: string
+  body: string
+}
+
+/** Payload of the game-crashed event */
+export interface CrashReport {
+  /** Unix timestamp in seconds of the last write to the log file */
+  last_activity: number | null
+  /** The last error lines of the log file, oldest first */
+  excerpt: string[]
+}
+
+/** Reason returned by the backend why a path can not be used as the log file */
+export type LogFileProblem =
+  | "NotFound"
+  | "NotAFile"
+  | { NotReadable: string }
+  | "Empty"
+  | { OtherGame: string }
+  | "NotACoh3Log"
+
+/** Result of the validate_log_file command */
+export interface LogFileReport {
+  path: string
+  exists: boolean
+  is_file: boolean
+  readable: boolean
+  size: number | null
+  /** Unix timestamp in seconds */
+  modified: number | null
+  is_coh3_log: boolean
+  game_running: boolean
+  /** Null when the file looks like a CoH3 warnings.log */
+  problem: LogFileProblem | null
+}
+
+/**
+ * The game may still write to an empty or unknown file, only paths that can
+ * not be read at all are rejected as the log file
+ */
+export const isLogFileUnusable = (problem: LogFileProblem | null) =>
+  problem === "NotFound" ||
+  problem === "NotAFile" ||
+  (typeof problem === "object" && problem !== null && "NotReadable" in problem)
+
+/** Explains to the user what is wrong with the log file */
+export const describeLogFileProblem = (problem: LogFileProblem): string => {
+  if (problem === "NotFound") return "File does not exist"
+  if (problem === "NotAFile") return "Path is not a file"
+  if (problem === "Empty") return "File is empty"
+  if (problem === "NotACoh3Log") return "File is not a CoH3 warnings.log"
+  if ("NotReadable" in problem)
+    return "File can not be read: " + problem.NotReadable
+  return "File is the log of Relic" + problem.OtherGame + ", not CoH3"
+}
+
 export interface FullPlayerData {
   ai: boolean
   self: boolean
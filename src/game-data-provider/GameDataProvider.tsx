@@ -1,11 +1,17 @@
 import React, { useContext } from "react"
 import { useLogFilePath } from "./configValues"
-import { GameData } from "./GameData"
+import { GameData, LogFileParseError } from "./GameData"
 import { useFullGameData } from "./useFullGameData"
 
 const GameDataContext = React.createContext<GameData>(undefined)
 export const useGameData = () => useContext(GameDataContext)
 
+const ParseErrorContext = React.createContext<LogFileParseError | undefined>(
+  undefined
+)
+/** The error of the last attempt to parse the log file, undefined once it was parsed */
+export const useLogFileParseError = () => useContext(ParseErrorContext)
+
 export interface GameDataProviderProps {
   children?: React.ReactNode
 }
@@ -13,7 +19,7 @@ export interface GameDataProviderProps {
 export const GameDataProvider: React.FC<GameDataProviderProps> = ({
   children,
 }) => {
-  const { gameData, reloadLogFile } = useFullGameData()
+  const { gameData, parseError, reloadLogFile } = useFullGameData()
   const [logFilePath] = useLogFilePath()
   return (
     <>
@@ -27,7 +33,9 @@ export const GameDataProvider: React.FC<GameDataProviderProps> = ({
             : undefined
         }
       >
-        {children}
+        <ParseErrorContext.Provider value={parseError}>
+          {children}
+        </ParseErrorContext.Provider>
       </GameDataContext.Provider>
     </>
   )
@@ -1,20 +1,23 @@
 import { configValueFactory } from "../config-store/configValueFactory"
 import { invoke } from "@tauri-apps/api/tauri"
+import { isLogFileUnusable, LogFileReport } from "./GameData"
+
+const isValidLogFile = async (path: string | undefined) => {
+  if (path === undefined) {
+    return false
+  }
+  const report = (await invoke("validate_log_file", { path })) as LogFileReport
+  return !isLogFileUnusable(report.problem)
+}
 
 const [getLogFilePath, useLogFilePath] = configValueFactory<string | undefined>(
   "logFilePath",
   async () => (await invoke("get_default_log_file_path")) as string,
   async (value, store, defaultValue) => {
-    const logFileExists = (await invoke("check_log_file_exists", {
-      path: value,
-    })) as boolean
-    if (logFileExists) {
+    if (await isValidLogFile(value)) {
       return value
     }
-    const defaultLogFileExists = (await invoke("check_log_file_exists", {
-      path: defaultValue,
-    })) as boolean
-    if (defaultLogFileExists) {
+    if (await isValidLogFile(defaultValue)) {
       return defaultValue
     }
 
@@ -22,4 +25,18 @@ const [getLogFilePath, useLogFilePath] = configValueFactory<string | undefined>(
   }
 )
 
-export { getLogFilePath, useLogFilePath }
+// read by the backend when it starts watching the log file
+const [getLogArchiveMaxFiles, useLogArchiveMaxFiles] =
+  configValueFactory<number>("logArchiveMaxFiles", async () => 30)
+
+const [getLogArchiveMaxMegabytes, useLogArchiveMaxMegabytes] =
+  configValueFactory<number>("logArchiveMaxMegabytes", async () => 100)
+
+export {
+  getLogFilePath,
+  useLogFilePath,
+  getLogArchiveMaxFiles,
+  useLogArchiveMaxFiles,
+  getLogArchiveMaxMegabytes,
+  useLogArchiveMaxMegabytes,
+}
@@ -1,18 +1,25 @@
-import React, { useEffect, useRef, useState } from "react"
+import React, { useEffect, useState } from "react"
 import { invoke } from "@tauri-apps/api/tauri"
-import { RawGameData } from "./GameData"
+import { listen } from "@tauri-apps/api/event"
+import { CrashReport, LogFileParseError, RawGameData } from "./GameData"
 import { useLogFilePath } from "./configValues"
 
 /** This hook handles the collection of raw game data from the log file */
 export const useRawGameData = () => {
   const [logFilePath] = useLogFilePath()
   const [rawGameData, setRawGameData] = useState<RawGameData>()
-  const intervalRef = useRef<number>()
+  const [parseError, setParseError] = useState<LogFileParseError>()
   const getLogFileData = async (path: string) => {
-    const data = (await invoke("parse_log_file_reverse", {
-      path,
-    })) as RawGameData
-    setRawGameData(data)
+    try {
+      const data = (await invoke("parse_log_file_tail", {
+        path,
+      })) as RawGameData
+      setRawGameData(data)
+      setParseError(undefined)
+    } catch (e) {
+      console.error(e)
+      setParseError(e as LogFileParseError)
+    }
   }
 
   const reloadLogFile = () => {
@@ -20,21 +27,36 @@ export const useRawGameData = () => {
       getLogFileData(logFilePath)
     }
   }
-  // when log file exists start watching the log file
+  // when log file exists let the backend watch it and emit changes
   useEffect(() => {
-    if (logFilePath !== undefined) {
-      if (intervalRef.current !== undefined) {
-        clearInterval(intervalRef.current)
-      }
-      intervalRef.current = setInterval(() => {
-        if (logFilePath !== undefined) {
-          getLogFileData(logFilePath)
-        }
-      }, 2000)
+    if (logFilePath === undefined) {
+      return
+    }
+    const unlistenPromises = [
+      listen<RawGameData>("game-state-changed", (event) => {
+        setRawGameData(event.payload)
+        setParseError(undefined)
+      }),
+      listen<LogFileParseError>("log-file-error", (event) => {
+        console.error(event.payload)
+        setParseError(event.payload)
+      }),
+      listen<CrashReport>("game-crashed", (event) => {
+        console.error("The game crashed", event.payload.excerpt)
+      }),
+    ]
+    Promise.all(unlistenPromises).then(() =>
+      invoke("watch_log_file", { path: logFilePath })
+    )
+    return () => {
+      unlistenPromises.forEach((unlistenPromise) =>
+        unlistenPromise.then((unlisten) => unlisten())
+      )
     }
   }, [logFilePath])
   return {
     rawGameData,
+    parseError,
     reloadLogFile,
   }
 }
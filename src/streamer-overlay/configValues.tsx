@@ -8,9 +8,21 @@ const [getShowFlagsOverlay, useShowFlagsOverlay] = configValueFactory<boolean>(
 const [getAlwaysShowOverlay, useAlwaysShowOverlay] =
   configValueFactory<boolean>("alwaysShowOverlay", async () => false)
 
+const [getOverlayServerEnabled, useOverlayServerEnabled] =
+  configValueFactory<boolean>("overlayServerEnabled", async () => false)
+
+const [getOverlayServerPort, useOverlayServerPort] = configValueFactory<number>(
+  "overlayServerPort",
+  async () => 47824
+)
+
 export {
   getShowFlagsOverlay,
   useShowFlagsOverlay,
   getAlwaysShowOverlay,
   useAlwaysShowOverlay,
+  getOverlayServerEnabled,
+  useOverlayServerEnabled,
+  getOverlayServerPort,
+  useOverlayServerPort,
 }
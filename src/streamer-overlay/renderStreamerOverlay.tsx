@@ -1,5 +1,6 @@
 import { FullGameData } from "../game-data-provider/GameData"
 import { BaseDirectory, writeTextFile } from "@tauri-apps/api/fs"
+import { invoke } from "@tauri-apps/api/tauri"
 import { renderToStaticMarkup, renderToString } from "react-dom/server"
 import { OverlayApp } from "./SPECIAL-REACT/OverlayApp"
 import { HTML } from "./SPECIAL-REACT/HTML"
@@ -13,8 +14,12 @@ export const renderStreamerHTML = async (gameData: FullGameData) => {
       alwaysVisible={await getAlwaysShowOverlay()}
     />
   )
-  const html = renderToStaticMarkup(<HTML html={content} />)
-  await writeTextFile("streamerOverlay.html", `<!doctype html>\n${html}`, {
+  const html = `<!doctype html>\n${renderToStaticMarkup(
+    <HTML html={content} />
+  )}`
+  await writeTextFile("streamerOverlay.html", html, {
     dir: BaseDirectory.AppData,
   })
+  // the overlay server serves the same page and reloads it in OBS
+  await invoke("set_overlay_html", { html })
 }